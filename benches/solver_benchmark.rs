@@ -2,15 +2,54 @@ use criterion::{criterion_group, criterion_main, Criterion};
 use iqfit_solver::board::BinaryBoard;
 use iqfit_solver::pieces::*;
 use iqfit_solver::puzzles::*;
-use iqfit_solver::solver;
+use iqfit_solver::solver::{self, DlxSolver, Pruning, SolveOptions, SolveOutcome, Solver};
 
 fn solve(pieces: &[PlacedPiece]) -> Vec<PlacedPiece> {
-    solver::solve::<BinaryBoard>(&pieces).unwrap()
+    solver::solve::<BinaryBoard>(pieces).unwrap()
+}
+
+fn solve_parallel(pieces: &[PlacedPiece]) -> Vec<PlacedPiece> {
+    solver::solve_parallel::<BinaryBoard>(pieces, solver::parallel::default_num_threads()).unwrap()
+}
+
+fn solve_dlx(pieces: &[PlacedPiece]) -> Vec<PlacedPiece> {
+    DlxSolver.solve(pieces).unwrap()
+}
+
+fn solve_with_pruning(pieces: &[PlacedPiece], pruning: Pruning) -> Vec<PlacedPiece> {
+    let options = SolveOptions::new().with_pruning(pruning);
+    match solver::solve_with_options::<BinaryBoard>(pieces, &options) {
+        SolveOutcome::Solved(solution) => solution,
+        outcome => panic!("Unexpected outcome: {:?}", outcome),
+    }
 }
 
 fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("solve 49", |b| b.iter(|| solve(&*PIECES_49)));
     c.bench_function("solve 117", |b| b.iter(|| solve(&*PIECES_117)));
+    c.bench_function("solve catalog", |b| {
+        b.iter(|| catalog().map(|p| solve(p.pieces)).collect::<Vec<_>>())
+    });
+    let regions = Pruning {
+        regions: true,
+        ..Pruning::default()
+    };
+    let regions_only = Pruning {
+        hole_patterns: false,
+        regions: true,
+        ..Pruning::default()
+    };
+    c.bench_function("solve 117 regions", |b| {
+        b.iter(|| solve_with_pruning(&*PIECES_117, regions))
+    });
+    c.bench_function("solve 117 regions only", |b| {
+        b.iter(|| solve_with_pruning(&*PIECES_117, regions_only))
+    });
+    c.bench_function("solve 49 dlx", |b| b.iter(|| solve_dlx(&*PIECES_49)));
+    c.bench_function("solve 117 dlx", |b| b.iter(|| solve_dlx(&*PIECES_117)));
+    c.bench_function("solve 117 parallel", |b| {
+        b.iter(|| solve_parallel(&*PIECES_117))
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
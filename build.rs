@@ -2,8 +2,11 @@ use std::env;
 use std::fs;
 use std::path::Path;
 
+include!("src/board/dimensions.rs");
 include!("src/board/display_board_placement_info.rs");
 
+/// The extra balls of each face of the pieces, in the Up orientation.
+/// The balls in column 0 lie on the board with the base line, the others stick up.
 fn get_pieces() -> Vec<Vec<(i8, i8)>> {
     // They all have (0, 0), (1, 0), (2, 0)
     let yellow_a: Vec<(i8, i8)> = vec![(0, 1), (3, 0)];
@@ -51,24 +54,25 @@ fn get_pieces() -> Vec<Vec<(i8, i8)>> {
 }
 
 /// Rotate a piece, and shift it so the top-left corner is at (0, 0).
-fn rotate_piece(balls: &mut Vec<(i8, i8)>) {
-    for b in balls.iter_mut() {
+/// Each ball is its coordinates, and whether it sticks up.
+fn rotate_piece(balls: &mut [((i8, i8), bool)]) {
+    for (b, _) in balls.iter_mut() {
         let tmp = -b.0;
         b.0 = b.1;
         b.1 = tmp;
     }
     balls.sort_unstable();
-    let top_left = balls[0];
-    for b in balls.iter_mut() {
+    let top_left = balls[0].0;
+    for (b, _) in balls.iter_mut() {
         b.0 -= top_left.0;
         b.1 -= top_left.1;
     }
-    assert!(balls[0] == (0, 0));
+    assert!(balls[0].0 == (0, 0));
 }
 
 fn coord_to_index(coords: (i8, i8)) -> u8 {
     assert!(coords.0 >= 0, "Negative row!");
-    (coords.0 * 10 + coords.1) as u8
+    (coords.0 * WIDTH as i8 + coords.1) as u8
 }
 
 fn coords_to_binary(coords: &[u8]) -> u64 {
@@ -79,16 +83,20 @@ fn coords_to_binary(coords: &[u8]) -> u64 {
     res
 }
 
-fn get_display_info(piece: &[(i8, i8)]) -> DisplayBoardPlacementInfo {
-    let max_row = piece.iter().map(|b| b.0).max().unwrap();
-    let min_col = piece.iter().map(|b| b.1).min().unwrap();
-    let max_col = piece.iter().map(|b| b.1).max().unwrap();
+fn get_display_info(piece: &[((i8, i8), bool)]) -> DisplayBoardPlacementInfo {
+    let max_row = piece.iter().map(|(b, _)| b.0).max().unwrap();
+    let min_col = piece.iter().map(|(b, _)| b.1).min().unwrap();
+    let max_col = piece.iter().map(|(b, _)| b.1).max().unwrap();
     assert!(min_col <= 0, "Got positive min_col: {}", min_col);
     assert!(max_col >= 0, "Got negative max_col: {}", max_col);
     assert!(max_row >= 0, "Got negative max_row: {}", max_row);
     let mut balls: [u8; 6] = [0; 6];
-    for i in 0..piece.len() {
-        balls[i] = coord_to_index(piece[i]);
+    let mut raised_balls = 0;
+    for (i, &(coords, raised)) in piece.iter().enumerate() {
+        balls[i] = coord_to_index(coords);
+        if raised {
+            raised_balls |= 1 << i;
+        }
     }
     DisplayBoardPlacementInfo {
         width_right: max_col as u8,
@@ -97,15 +105,26 @@ fn get_display_info(piece: &[(i8, i8)]) -> DisplayBoardPlacementInfo {
         num_balls: piece.len() as u8,
         balls,
         as_binary: coords_to_binary(&balls),
+        raised_balls,
     }
 }
 
-fn write_piece<T: std::io::Write>(file: &mut T, piece: &[(i8, i8)]) {
-    let info = get_display_info(piece);
-    writeln!(file, "    &{:#?},", info).unwrap();
+fn get_placement_infos() -> Vec<DisplayBoardPlacementInfo> {
+    let mut infos = Vec::with_capacity(80);
+    for p in get_pieces().iter() {
+        let mut balls: Vec<((i8, i8), bool)> = p.iter().map(|&b| (b, b.1 != 0)).collect();
+        for i in 0..3 {
+            balls.push(((i, 0), false));
+        }
+        for _ in 0..4 {
+            infos.push(get_display_info(&balls));
+            rotate_piece(&mut balls);
+        }
+    }
+    infos
 }
 
-fn write_pieces<T: std::io::Write>(file: &mut T) {
+fn write_pieces<T: std::io::Write>(file: &mut T, infos: &[DisplayBoardPlacementInfo]) {
     write!(
         file,
         "
@@ -115,18 +134,51 @@ pub const PLACEMENT_INFO: &[&DisplayBoardPlacementInfo; 80] = &["
     )
     .unwrap();
 
-    for p in get_pieces().iter_mut() {
-        for i in 0..3 {
-            p.push((i, 0));
-        }
-        for _ in 0..4 {
-            write_piece(file, p);
-            rotate_piece(p);
-        }
+    for info in infos {
+        writeln!(file, "    &{:#?},", info).unwrap();
     }
     write!(file, "];").unwrap();
 }
 
+fn is_valid_placement(top_left: u8, info: &DisplayBoardPlacementInfo) -> bool {
+    let (row, col) = (top_left / WIDTH, top_left % WIDTH);
+    row + info.height < HEIGHT && col + info.width_right < WIDTH && col >= info.width_left
+}
+
+/// Write, for each cell and each color, the placements of the piece that fit on the board with
+/// their top-left ball on that cell.
+fn write_placement_table<T: std::io::Write>(file: &mut T, infos: &[DisplayBoardPlacementInfo]) {
+    write!(
+        file,
+        "
+use crate::board::Placement;
+
+pub const PLACEMENTS: [[&[Placement]; 10]; {}] = [",
+        NUM_CELLS
+    )
+    .unwrap();
+    for top_left in 0..NUM_CELLS {
+        write!(file, "\n    [").unwrap();
+        for (color, color_infos) in infos.chunks(8).enumerate() {
+            write!(file, "\n        &[").unwrap();
+            for (i, info) in color_infos.iter().enumerate() {
+                if is_valid_placement(top_left, info) {
+                    write!(
+                        file,
+                        "\n            Placement {{ piece: {}, mask: {:#x} }},",
+                        color * 8 + i,
+                        info.as_binary << top_left
+                    )
+                    .unwrap();
+                }
+            }
+            write!(file, "\n        ],").unwrap();
+        }
+        write!(file, "\n    ],").unwrap();
+    }
+    write!(file, "\n];").unwrap();
+}
+
 fn first_unset_bit(byte: u8) -> u8 {
     for i in 0..8 {
         if byte & (1 << i) == 0 {
@@ -160,10 +212,16 @@ fn main() -> std::io::Result<()> {
     let out_dir = env::var_os("OUT_DIR").unwrap();
     let dest_path = Path::new(&out_dir).join("display_board_placement_info_gen.rs");
     let mut file = fs::File::create(&dest_path)?;
-    write_pieces(&mut file);
+    let infos = get_placement_infos();
+    write_pieces(&mut file, &infos);
+    let dest_path = Path::new(&out_dir).join("placement_table_gen.rs");
+    let mut file = fs::File::create(&dest_path)?;
+    write_placement_table(&mut file, &infos);
     let dest_path = Path::new(&out_dir).join("first_unset_bit_table.rs");
     let mut file = fs::File::create(&dest_path)?;
     write_first_unset_bit_table(&mut file);
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/board/dimensions.rs");
+    println!("cargo:rerun-if-changed=src/board/display_board_placement_info.rs");
     Ok(())
 }
@@ -1,15 +1,189 @@
 use crate::pieces::*;
+use std::fmt;
 
 pub mod binary_board;
+mod dimensions;
 pub mod display_board;
 mod display_board_placement_info;
 mod display_board_placement_info_gen;
+mod geometry;
+pub mod layers;
+mod placement_table;
+pub mod wide_binary_board;
 
 pub use binary_board::BinaryBoard;
+pub use dimensions::{HEIGHT, NUM_CELLS, WIDTH};
 pub use display_board::DisplayBoard;
+pub use geometry::BoardGeometry;
+pub use layers::{Layer, LayeredBoard};
+pub use wide_binary_board::WideBinaryBoard;
 
 use display_board_placement_info::DisplayBoardPlacementInfo;
 
+/// A shape of empty cells that no piece can fill, making the board unsolvable.
+/// The variants are named after the size and shape of the enclosed hole.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FailurePattern {
+    SingleHole,
+    DoubleHole,
+    DoubleHoleVertical,
+    TripleHole,
+    TripleHoleL,
+    TripleHoleL2,
+    TripleHoleL3,
+    TripleHoleL4,
+}
+
+impl FailurePattern {
+    pub const COUNT: usize = 8;
+    pub const ALL: [FailurePattern; FailurePattern::COUNT] = [
+        FailurePattern::SingleHole,
+        FailurePattern::DoubleHole,
+        FailurePattern::DoubleHoleVertical,
+        FailurePattern::TripleHole,
+        FailurePattern::TripleHoleL,
+        FailurePattern::TripleHoleL2,
+        FailurePattern::TripleHoleL3,
+        FailurePattern::TripleHoleL4,
+    ];
+}
+
+/// Why a piece can't be placed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PlacementError {
+    /// The piece with its top-left ball on `cell` doesn't fit on the board.
+    OutOfBounds { cell: u8 },
+    /// The piece covers `cell`, which is already covered by the piece of `color`.
+    Overlap { cell: u8, color: Color },
+    /// A piece of this color is already on the board.
+    DuplicateColor(Color),
+    /// There are more pieces on this face than a solution allows.
+    FaceQuotaExceeded(Face),
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlacementError::OutOfBounds { cell } => {
+                write!(f, "the piece on cell {} doesn't fit on the board", cell)
+            }
+            PlacementError::Overlap { cell, color } => {
+                write!(
+                    f,
+                    "cell {} is already covered by the {:?} piece",
+                    cell, color
+                )
+            }
+            PlacementError::DuplicateColor(color) => {
+                write!(f, "the {:?} piece is already on the board", color)
+            }
+            PlacementError::FaceQuotaExceeded(face) => {
+                write!(f, "too many pieces on face {:?}", face)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlacementError {}
+
+/// A piece placed with its top-left ball on a given cell, precomputed by the build script or by a
+/// `PlacementTable`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Placement {
+    /// The piece, as given by `Piece::as_byte`.
+    pub piece: u8,
+    /// The cells covered by the piece, as a bitfield.
+    pub mask: u64,
+}
+
+impl Placement {
+    #[inline]
+    pub fn piece(&self) -> Piece {
+        Piece::from_byte(self.piece)
+    }
+}
+
+/// The placements of the pieces of the given color that fit on the board with their top-left
+/// ball on `top_left`. They are ordered by face, then orientation.
+#[inline]
+pub fn placements(top_left: u8, color: Color) -> &'static [Placement] {
+    placement_table::PLACEMENTS[top_left as usize][color as usize]
+}
+
+/// The placements of the pieces of a `PieceSet` on the board, like `placements` for the standard
+/// pieces. The piece numbers of the set are stored as colors, see `PieceSet::piece`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlacementTable {
+    /// The placements by top-left cell, then piece number.
+    placements: Vec<Vec<Vec<Placement>>>,
+    /// The number of balls of each piece, indexed by `Piece::as_byte`.
+    num_balls: Vec<u8>,
+}
+
+impl PlacementTable {
+    pub fn new(pieces: &PieceSet) -> Self {
+        let geometry = BoardGeometry::STANDARD;
+        let bytes = 0..8 * pieces.len() as u8;
+        let placements = (0..NUM_CELLS)
+            .map(|top_left| {
+                let mut by_piece = vec![Vec::new(); pieces.len()];
+                for byte in bytes.clone() {
+                    let piece = PlacedPiece {
+                        piece: Piece::from_byte(byte),
+                        top_left,
+                    };
+                    if let Some(cells) = pieces.piece_cells(&geometry, piece) {
+                        by_piece[byte as usize / 8].push(Placement {
+                            piece: byte,
+                            mask: cells.iter().fold(0, |mask, &cell| mask | 1 << cell),
+                        });
+                    }
+                }
+                by_piece
+            })
+            .collect();
+        let num_balls = bytes
+            .map(|byte| pieces.num_balls(Piece::from_byte(byte)) as u8)
+            .collect();
+        PlacementTable {
+            placements,
+            num_balls,
+        }
+    }
+
+    /// The placements of the piece of the set numbered by `color` that fit on the board with
+    /// their top-left ball on `top_left`. They are ordered by face, then orientation.
+    pub fn placements(&self, top_left: u8, color: Color) -> &[Placement] {
+        self.placements[top_left as usize]
+            .get(color as usize)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The placement of the piece, or None if it doesn't fit on the board or isn't in the set.
+    pub fn placement(&self, piece: PlacedPiece) -> Option<Placement> {
+        if piece.top_left >= NUM_CELLS {
+            return None;
+        }
+        self.placements(piece.top_left, piece.piece.color())
+            .iter()
+            .find(|p| p.piece == piece.piece.as_byte())
+            .copied()
+    }
+
+    /// The number of balls of the piece, 0 if it isn't in the set.
+    pub fn num_balls(&self, piece: Piece) -> u8 {
+        self.num_balls
+            .get(piece.as_byte() as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The smallest number of balls of a piece of the set, on either face.
+    pub fn min_balls(&self) -> u8 {
+        self.num_balls.iter().copied().min().unwrap_or(0)
+    }
+}
+
 /// Represents a board on which you can place pieces.
 pub trait Board: Sized + Copy + Default {
     /// Try to place a piece and return whether it succeeded.
@@ -19,6 +193,15 @@ pub trait Board: Sized + Copy + Default {
     fn can_place_piece(&self, piece: PlacedPiece) -> bool;
     fn with_piece(self, piece: PlacedPiece) -> Self;
     fn maybe_with_piece(&self, piece: PlacedPiece) -> Option<Self>;
+    /// Like `maybe_with_piece`, for a placement from `placements(top_left, _)` or from a
+    /// `PlacementTable`. The boards that use the mask of the placement, rather than the shape
+    /// of its piece, can hold the pieces of any `PieceSet`.
+    fn maybe_with_placement(&self, placement: &Placement, top_left: u8) -> Option<Self> {
+        self.maybe_with_piece(PlacedPiece {
+            piece: placement.piece(),
+            top_left,
+        })
+    }
     /// Try to place a piece in the first empty spot in the top left and return whether it
     /// succeeded.
     fn place_piece_top_left(&mut self, piece: Piece) -> bool {
@@ -44,27 +227,114 @@ pub trait Board: Sized + Copy + Default {
     fn is_cell_empty(&self, index: u8) -> bool;
     /// Check for common failure patterns.
     fn check_common_failures(&self) -> bool;
+    /// Like `check_common_failures`, but also says which pattern was found.
+    fn common_failure(&self) -> Option<FailurePattern>;
     /// Create an empty board.
     fn empty() -> Self;
+    /// The bitfield of the empty cells, starting from the top left and going row by row.
+    fn empty_cells(&self) -> u64;
     fn from_piece_list(pieces: &[Piece]) -> Option<Self> {
-        let mut board = Self::default();
-        for p in pieces {
-            if !board.place_piece_top_left(*p) {
-                return None;
-            }
-        }
-        Some(board)
+        Self::try_from_piece_list(pieces).ok()
     }
     fn from_placed_piece_list(pieces: &[PlacedPiece]) -> Option<Self> {
+        Self::try_from_placed_piece_list(pieces).ok()
+    }
+    /// Like `from_piece_list`, but says why a piece can't be placed.
+    fn try_from_piece_list(pieces: &[Piece]) -> Result<Self, PlacementError> {
+        let mut placed = Vec::with_capacity(pieces.len());
         let mut board = Self::empty();
         for p in pieces {
-            if !board.can_place_piece(*p) {
-                return None;
-            }
+            let piece = PlacedPiece {
+                piece: *p,
+                top_left: board.first_empty_cell(0).unwrap_or(NUM_CELLS),
+            };
+            check_placement(&placed, piece)?;
+            board = board.with_piece(piece);
+            placed.push(piece);
+        }
+        Ok(board)
+    }
+    /// Like `from_placed_piece_list`, but says why a piece can't be placed.
+    fn try_from_placed_piece_list(pieces: &[PlacedPiece]) -> Result<Self, PlacementError> {
+        let mut board = Self::empty();
+        for (i, p) in pieces.iter().enumerate() {
+            check_placement(&pieces[..i], *p)?;
             board = board.with_piece(*p);
         }
-        Some(board)
+        Ok(board)
+    }
+}
+
+/// Check that `piece` fits on the board next to the `placed` pieces.
+pub(crate) fn check_placement(
+    placed: &[PlacedPiece],
+    piece: PlacedPiece,
+) -> Result<(), PlacementError> {
+    let mask = placement_mask(piece).ok_or(PlacementError::OutOfBounds {
+        cell: piece.top_left,
+    })?;
+    for other in placed {
+        if other.piece.color() == piece.piece.color() {
+            return Err(PlacementError::DuplicateColor(piece.piece.color()));
+        }
+        let overlap = placement_mask(*other).unwrap_or(0) & mask;
+        if overlap != 0 {
+            return Err(PlacementError::Overlap {
+                cell: overlap.trailing_zeros() as u8,
+                color: other.piece.color(),
+            });
+        }
     }
+    Ok(())
+}
+
+/// The cells in the first column of the board.
+const FIRST_COLUMN: u64 = first_column();
+/// The cells in the last column of the board.
+const LAST_COLUMN: u64 = FIRST_COLUMN << (WIDTH - 1);
+
+const fn first_column() -> u64 {
+    let mut column = 0;
+    let mut row = 0;
+    while row < HEIGHT {
+        column |= 1 << (row * WIDTH);
+        row += 1;
+    }
+    column
+}
+
+/// Call `f` on each connected region of the `empty` cells, given as a bitfield, until it returns
+/// false. Returns false if `f` did.
+///
+/// The regions are found with a bit-parallel flood fill, starting from the top-left-most cell.
+pub fn for_each_empty_region<F: FnMut(u64) -> bool>(empty: u64, mut f: F) -> bool {
+    let mut cells_left = empty;
+    while cells_left != 0 {
+        let mut region = cells_left & cells_left.wrapping_neg();
+        loop {
+            let grown = (region
+                | ((region << 1) & !FIRST_COLUMN)
+                | ((region >> 1) & !LAST_COLUMN)
+                | (region << WIDTH)
+                | (region >> WIDTH))
+                & empty;
+            if grown == region {
+                break;
+            }
+            region = grown;
+        }
+        cells_left &= !region;
+        if !f(region) {
+            return false;
+        }
+    }
+    true
+}
+
+/// The number of balls of the piece, i.e. the number of cells it covers.
+#[inline]
+pub(crate) const fn num_balls(piece: Piece) -> u8 {
+    get_placement_info(piece).num_balls
 }
 
 #[inline]
@@ -75,17 +345,29 @@ const fn get_placement_info(piece: Piece) -> &'static DisplayBoardPlacementInfo
 #[inline]
 const fn is_valid_piece_placement(piece: PlacedPiece, info: &DisplayBoardPlacementInfo) -> bool {
     let (top_left_row, top_left_col) = piece.top_left_coords();
-    top_left_row + info.height < 5
-        && top_left_col + info.width_right < 10
+    top_left_row + info.height < HEIGHT
+        && top_left_col + info.width_right < WIDTH
         && top_left_col >= info.width_left
 }
 
+/// The bitfield of the cells covered by a piece, or None if it doesn't fit on the board.
+#[inline]
+pub(crate) fn placement_mask(piece: PlacedPiece) -> Option<u64> {
+    let info = get_placement_info(piece.piece);
+    if is_valid_piece_placement(piece, info) {
+        Some(info.as_binary << piece.top_left)
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 #[generic_tests::define]
 mod tests {
     use crate::board::*;
 
     #[test]
+    #[allow(clippy::extra_unused_type_parameters)]
     fn pink_display_info<B>()
     where
         B: Board,
@@ -105,6 +387,7 @@ mod tests {
                 num_balls: 5,
                 balls: [0, 1, 2, 3, 12, 0],
                 as_binary: 0b1000000001111,
+                raised_balls: 0b10000,
             }
         );
     }
@@ -112,9 +395,9 @@ mod tests {
     #[test]
     fn fail_place_piece<B>()
     where
-        B: Board,
+        B: Board + PartialEq + std::fmt::Debug,
     {
-        let board = DisplayBoard::empty();
+        let board = B::empty();
         // Too far right.
         assert!(!board.can_place_piece(PlacedPiece {
             piece: Piece::new()
@@ -140,15 +423,15 @@ mod tests {
             top_left: 20,
         }));
 
-        assert_eq!(board, DisplayBoard::empty());
+        assert_eq!(board, B::empty());
     }
 
     #[test]
     fn fail_place_piece_intersect<B>()
     where
-        B: Board,
+        B: Board + PartialEq + std::fmt::Debug,
     {
-        let mut board = DisplayBoard::empty();
+        let mut board = B::empty();
         let pink_piece = PlacedPiece {
             piece: Piece::new()
                 .with_color(Color::Pink)
@@ -186,12 +469,54 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn placement_errors<B>()
+    where
+        B: Board + PartialEq + std::fmt::Debug,
+    {
+        let piece = |color, top_left| PlacedPiece {
+            piece: Piece::new()
+                .with_color(color)
+                .with_face(Face::A)
+                .with_orientation(Orientation::Right),
+            top_left,
+        };
+        assert_eq!(
+            B::try_from_placed_piece_list(&[piece(Color::Pink, 22), piece(Color::Yellow, 7)]),
+            Err(PlacementError::OutOfBounds { cell: 7 })
+        );
+        assert_eq!(
+            B::try_from_placed_piece_list(&[piece(Color::Pink, 22), piece(Color::Yellow, 21)]),
+            Err(PlacementError::Overlap {
+                cell: 22,
+                color: Color::Pink
+            })
+        );
+        assert_eq!(
+            B::try_from_placed_piece_list(&[piece(Color::Pink, 22), piece(Color::Pink, 0)]),
+            Err(PlacementError::DuplicateColor(Color::Pink))
+        );
+        assert_eq!(
+            B::from_placed_piece_list(&[piece(Color::Pink, 22), piece(Color::Pink, 0)]),
+            None
+        );
+        let pieces = [piece(Color::Pink, 22), piece(Color::Yellow, 0)];
+        assert_eq!(
+            B::try_from_placed_piece_list(&pieces),
+            Ok(B::from_placed_piece_list(&pieces).unwrap())
+        );
+        assert_eq!(
+            B::try_from_piece_list(&[pieces[1].piece, pieces[1].piece]),
+            Err(PlacementError::DuplicateColor(Color::Yellow))
+        );
+    }
+
     #[test]
     fn success_fill_board<B>()
     where
-        B: Board,
+        B: Board + PartialEq + std::fmt::Debug,
     {
-        let mut board = DisplayBoard::empty();
+        let mut board = B::empty();
         // Solution 49.
         let pieces = vec![
             PlacedPiece {
@@ -269,13 +594,90 @@ mod tests {
             assert!(board.can_place_piece(*p));
             board = board.with_piece(*p);
         }
-        let new_board = DisplayBoard::from_placed_piece_list(&pieces);
+        let new_board = B::from_placed_piece_list(&pieces);
         assert!(new_board.is_some());
         assert_eq!(board, new_board.unwrap());
     }
 
+    #[test]
+    fn empty_regions<B>()
+    where
+        B: Board + PartialEq + std::fmt::Debug,
+    {
+        assert_eq!(B::empty().empty_cells(), (1 << 50) - 1);
+        let mut regions = Vec::new();
+        for_each_empty_region(B::empty().empty_cells(), |r| {
+            regions.push(r);
+            true
+        });
+        assert_eq!(regions, vec![(1 << 50) - 1]);
+
+        // Wall off the first two columns of the top three rows, and the first three cells of
+        // the bottom row.
+        let board = B::from_placed_piece_list(&[
+            PlacedPiece {
+                piece: Piece::new()
+                    .with_color(Color::Green)
+                    .with_face(Face::A)
+                    .with_orientation(Orientation::Up),
+                top_left: 2,
+            },
+            PlacedPiece {
+                piece: Piece::new()
+                    .with_color(Color::Yellow)
+                    .with_face(Face::A)
+                    .with_orientation(Orientation::Right),
+                top_left: 30,
+            },
+        ])
+        .unwrap();
+        regions.clear();
+        for_each_empty_region(board.empty_cells(), |r| {
+            regions.push(r);
+            true
+        });
+        assert_eq!(
+            regions.iter().map(|r| r.count_ones()).collect::<Vec<_>>(),
+            vec![6, 32, 3]
+        );
+        assert_eq!(regions[0], 0b11 | 0b11 << 10 | 0b11 << 20);
+    }
+
+    #[test]
+    fn placement_table<B>()
+    where
+        B: Board + PartialEq + std::fmt::Debug,
+    {
+        let board = B::empty();
+        for top_left in 0..50 {
+            for byte in 0..80 {
+                let piece = PlacedPiece {
+                    piece: Piece::from_byte(byte),
+                    top_left,
+                };
+                let placement = placements(top_left, piece.piece.color())
+                    .iter()
+                    .find(|p| p.piece == byte);
+                assert_eq!(
+                    placement.map(|p| p.mask),
+                    placement_mask(piece),
+                    "{:?}",
+                    piece
+                );
+                if let Some(placement) = placement {
+                    assert_eq!(
+                        board.maybe_with_placement(placement, top_left),
+                        board.maybe_with_piece(piece)
+                    );
+                }
+            }
+        }
+    }
+
     #[instantiate_tests(<DisplayBoard>)]
     mod display_board {}
     #[instantiate_tests(<BinaryBoard>)]
     mod binary_board {}
+    #[instantiate_tests(<WideBinaryBoard>)]
+    mod wide_binary_board {}
 }
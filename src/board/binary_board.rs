@@ -1,6 +1,9 @@
 use crate::board::*;
 
 /// Model the board as a bitfield of empty cells.
+///
+/// The bitfield and the failure patterns are laid out for the standard board, whose cells fit
+/// in a `u64` with rows of 10 cells. `WideBinaryBoard` models larger boards.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct BinaryBoard {
     /// Bitfield of empty cells. Each bit corresponds to a specific cell, starting from the top
@@ -32,13 +35,28 @@ impl Board for BinaryBoard {
             None
         }
     }
+    #[inline]
+    fn maybe_with_placement(&self, placement: &Placement, _top_left: u8) -> Option<Self> {
+        if self.cells & placement.mask != 0 {
+            None
+        } else {
+            Some(BinaryBoard {
+                cells: self.cells | placement.mask,
+            })
+        }
+    }
     fn empty() -> Self {
         BinaryBoard {
             // Set the cells after the board to full.
-            cells: (!0) << 50,
+            cells: (!0) << NUM_CELLS,
         }
     }
 
+    #[inline]
+    fn empty_cells(&self) -> u64 {
+        !self.cells
+    }
+
     #[inline]
     fn is_cell_empty(&self, index: u8) -> bool {
         self.cells & (1 << index) == 0
@@ -46,6 +64,40 @@ impl Board for BinaryBoard {
 
     #[inline]
     fn check_common_failures(&self) -> bool {
+        self.failure_masks().iter().fold(0, |acc, mask| acc | mask) != 0
+    }
+
+    fn common_failure(&self) -> Option<FailurePattern> {
+        let masks = self.failure_masks();
+        FailurePattern::ALL
+            .iter()
+            .zip(masks.iter())
+            .find(|(_, &mask)| mask != 0)
+            .map(|(&pattern, _)| pattern)
+    }
+
+    #[inline]
+    fn first_empty_cell(&self, lower_bound: u8) -> Option<u8> {
+        let first_unset_bit = self.get_first_unset_bit(lower_bound);
+        if first_unset_bit >= NUM_CELLS {
+            None
+        } else {
+            Some(first_unset_bit)
+        }
+    }
+}
+
+impl Default for BinaryBoard {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl BinaryBoard {
+    /// Look for the failure patterns, in the order of `FailurePattern::ALL`.
+    /// Each mask is non-zero iff the pattern was found.
+    #[inline]
+    fn failure_masks(&self) -> [u64; FailurePattern::COUNT] {
         let n = self.cells;
         // Prepare the following pattern:
         //  #
@@ -55,7 +107,7 @@ impl Board for BinaryBoard {
         //  #
         // #.#
         //  #
-        let single_hole = (base_pattern & (n << 20) & (n << 11)) != 0;
+        let single_hole = base_pattern & (n << 20) & (n << 11);
         // Prepare the following pattern:
         //  ##
         // #..
@@ -65,97 +117,78 @@ impl Board for BinaryBoard {
         //  ##
         // #..#
         //  ##
-        let double_hole = (base_double_pattern & (n << 12) & (n << 21)) != 0;
+        let double_hole = base_double_pattern & (n << 12) & (n << 21);
         // Look for the following pattern:
         //  #
         // #.#
         // #.#
         //  #
         let double_hole_vertical =
-            (base_pattern & (n << 11) & (!n << 20) & (n << 19) & (n << 21) & (n << 30)) != 0;
+            base_pattern & (n << 11) & (!n << 20) & (n << 19) & (n << 21) & (n << 30);
         // Look for the following pattern:
         //  ###
         // #...#
         //  ###
         let triple_hole =
-            (base_double_pattern & (!n << 12) & (n << 21) & (n << 3) & (n << 22) & (n << 13)) != 0;
+            base_double_pattern & (!n << 12) & (n << 21) & (n << 2) & (n << 22) & (n << 13);
         // Look for the following pattern:
         //  ##
         // #..#
         //  #.#
         //   #
         let triple_hole_l =
-            (base_double_pattern & (n << 12) & (!n << 21) & (n << 31) & (n << 22) & (n << 13)) != 0;
+            base_double_pattern & (n << 12) & (!n << 21) & (n << 31) & (n << 22) & (n << 13);
         // Look for the following pattern:
         //  ##
         // #..#
         // #.#
         //  #
-        let triple_hole_l2 = (base_pattern
+        let triple_hole_l2 = base_pattern
             & (n << 1)
             & (!n << 11)
             & (n << 12)
             & (n << 19)
             & (!n << 20)
             & (n << 21)
-            & (n << 30))
-            != 0;
+            & (n << 30);
         // Look for the following pattern:
         //  #
         // #.#
         // #..#
         //  ##
-        let triple_hole_l3 = (base_pattern
+        let triple_hole_l3 = base_pattern
             & (n << 11)
             & (n << 19)
             & (!n << 20)
             & (!n << 21)
             & (n << 22)
             & (n << 30)
-            & (n << 31))
-            != 0;
+            & (n << 31);
         // Look for the following pattern:
         //   #
         //  #.#
         // #..#
         //  ##
-        let triple_hole_l4 = (base_pattern
+        let triple_hole_l4 = base_pattern
             & (n << 11)
             & (n << 18)
             & (!n << 19)
             & (!n << 20)
             & (n << 21)
             & (n << 29)
-            & (n << 30))
-            != 0;
-        single_hole
-            || double_hole
-            || double_hole_vertical
-            || triple_hole
-            || triple_hole_l
-            || triple_hole_l2
-            || triple_hole_l3
-            || triple_hole_l4
+            & (n << 30);
+        [
+            single_hole,
+            double_hole,
+            double_hole_vertical,
+            triple_hole,
+            triple_hole_l,
+            triple_hole_l2,
+            triple_hole_l3,
+            triple_hole_l4,
+        ]
     }
 
-    #[inline]
-    fn first_empty_cell(&self, lower_bound: u8) -> Option<u8> {
-        let first_unset_bit = self.get_first_unset_bit(lower_bound);
-        if first_unset_bit >= 50 {
-            None
-        } else {
-            Some(first_unset_bit)
-        }
-    }
-}
-
-impl Default for BinaryBoard {
-    fn default() -> Self {
-        Self::empty()
-    }
-}
-
-impl BinaryBoard {
     /// Find the first bit left unset in the bitfield.
     /// This uses a lookup table to get the first unset bit in a byte efficiently.
     #[inline]
@@ -171,7 +204,7 @@ impl BinaryBoard {
                 return first_empty_cell_byte_index * 8 + first_unset_bit;
             }
         }
-        50
+        NUM_CELLS
     }
 }
 
@@ -0,0 +1,8 @@
+// Keep these definitions alone in this file, they are included by the build script.
+
+/// The number of columns of the board.
+pub const WIDTH: u8 = 10;
+/// The number of rows of the board.
+pub const HEIGHT: u8 = 5;
+/// The number of cells of the board.
+pub const NUM_CELLS: u8 = WIDTH * HEIGHT;
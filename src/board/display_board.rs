@@ -3,7 +3,7 @@ use std::fmt;
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct DisplayBoard {
-    cells: [Option<Color>; 50],
+    cells: [Option<Color>; NUM_CELLS as usize],
 }
 
 impl Board for DisplayBoard {
@@ -35,39 +35,63 @@ impl Board for DisplayBoard {
             None
         }
     }
+    fn maybe_with_placement(&self, placement: &Placement, _top_left: u8) -> Option<Self> {
+        let mut board = *self;
+        let color = placement.piece().color();
+        let mut mask = placement.mask;
+        while mask != 0 {
+            let cell = &mut board.cells[mask.trailing_zeros() as usize];
+            if cell.is_some() {
+                return None;
+            }
+            *cell = Some(color);
+            mask &= mask - 1;
+        }
+        Some(board)
+    }
     fn is_cell_empty(&self, index: u8) -> bool {
-        if index >= 50 {
+        if index >= NUM_CELLS {
             false
         } else {
             self.cells[index as usize].is_none()
         }
     }
     fn check_common_failures(&self) -> bool {
-        let index = self.first_empty_cell(0);
-        if index.is_none() {
-            return false;
-        }
-        let index = index.unwrap();
-        if !self.is_cell_empty(index + 10) {
-            if index % 10 == 9 || !self.is_cell_empty(index + 1) {
-                return true;
+        self.common_failure().is_some()
+    }
+    fn common_failure(&self) -> Option<FailurePattern> {
+        let index = self.first_empty_cell(0)?;
+        if !self.is_cell_empty(index + WIDTH) {
+            if index % WIDTH == WIDTH - 1 || !self.is_cell_empty(index + 1) {
+                return Some(FailurePattern::SingleHole);
             }
-            if !self.is_cell_empty(index + 11) && !self.is_cell_empty(index + 2) {
-                return true;
+            if !self.is_cell_empty(index + WIDTH + 1) && !self.is_cell_empty(index + 2) {
+                return Some(FailurePattern::DoubleHole);
             }
         }
-        false
+        None
     }
     fn empty() -> Self {
-        DisplayBoard { cells: [None; 50] }
+        DisplayBoard {
+            cells: [None; NUM_CELLS as usize],
+        }
+    }
+    fn empty_cells(&self) -> u64 {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.is_none())
+            .fold(0, |acc, (i, _)| acc | 1 << i)
     }
 
     fn first_empty_cell(&self, lower_bound: u8) -> Option<u8> {
         let mut first_empty_cell_index = lower_bound;
-        while first_empty_cell_index < 50 && self.cells[first_empty_cell_index as usize].is_some() {
+        while first_empty_cell_index < NUM_CELLS
+            && self.cells[first_empty_cell_index as usize].is_some()
+        {
             first_empty_cell_index += 1;
         }
-        if first_empty_cell_index == 50 {
+        if first_empty_cell_index == NUM_CELLS {
             None
         } else {
             Some(first_empty_cell_index)
@@ -76,6 +100,27 @@ impl Board for DisplayBoard {
 }
 
 impl DisplayBoard {
+    /// Like `maybe_with_piece`, but says why the piece can't be placed.
+    pub fn try_with_piece(&self, piece: PlacedPiece) -> Result<Self, PlacementError> {
+        let color = piece.piece.color();
+        if self.cells.contains(&Some(color)) {
+            return Err(PlacementError::DuplicateColor(color));
+        }
+        let info = get_placement_info(piece.piece);
+        if !is_valid_piece_placement(piece, info) {
+            return Err(PlacementError::OutOfBounds {
+                cell: piece.top_left,
+            });
+        }
+        for i in 0..info.num_balls as usize {
+            let cell = piece.top_left + info.balls[i];
+            if let Some(color) = *self.cell_at(cell) {
+                return Err(PlacementError::Overlap { cell, color });
+            }
+        }
+        Ok(self.with_piece(piece))
+    }
+
     fn cell_at(&self, index: u8) -> &Option<Color> {
         &self.cells[index as usize]
     }
@@ -93,30 +138,143 @@ impl Default for DisplayBoard {
 
 impl fmt::Display for DisplayBoard {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use colored::Colorize;
-        for row in 0..5 {
-            for col in 0..10 {
-                let index = row * 10 + col;
-                write!(
-                    f,
-                    "{}",
-                    match self.cells[index] {
-                        None => "  ".on_black(),
-                        Some(Color::Yellow) => "  ".on_yellow(),
-                        Some(Color::Orange) => "  ".on_bright_red(),
-                        Some(Color::Red) => "  ".on_red(),
-                        Some(Color::Pink) => "  ".on_bright_purple(),
-                        Some(Color::LightGreen) => "  ".on_bright_green(),
-                        Some(Color::Green) => "  ".on_green(),
-                        Some(Color::LightBlue) => "  ".on_bright_cyan(),
-                        Some(Color::Blue) => "  ".on_bright_blue(),
-                        Some(Color::DeepBlue) => "  ".on_blue(),
-                        Some(Color::Purple) => "  ".on_purple(),
-                    }
-                )?;
+        for row in 0..HEIGHT as usize {
+            for col in 0..WIDTH as usize {
+                write!(f, "{}", paint("  ", self.cells[row * WIDTH as usize + col]))?;
             }
             writeln!(f)?;
         }
         Ok(())
     }
 }
+
+/// The text on the background of the color of the cell, for the terminal.
+pub(crate) fn paint(text: &str, cell: Option<Color>) -> colored::ColoredString {
+    use colored::Colorize;
+    match cell {
+        None => text.on_black(),
+        Some(Color::Yellow) => text.on_yellow(),
+        Some(Color::Orange) => text.on_bright_red(),
+        Some(Color::Red) => text.on_red(),
+        Some(Color::Pink) => text.on_bright_purple(),
+        Some(Color::LightGreen) => text.on_bright_green(),
+        Some(Color::Green) => text.on_green(),
+        Some(Color::LightBlue) => text.on_bright_cyan(),
+        Some(Color::Blue) => text.on_bright_blue(),
+        Some(Color::DeepBlue) => text.on_blue(),
+        Some(Color::Purple) => text.on_purple(),
+    }
+}
+
+/// A board is serialized as its `HEIGHT` rows, in the grid format of `puzzles::format`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for DisplayBoard {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use crate::puzzles::format::color_letter;
+        use serde::ser::SerializeSeq;
+        let mut seq = serializer.serialize_seq(Some(HEIGHT as usize))?;
+        for row in self.cells.chunks(WIDTH as usize) {
+            let row: String = row
+                .iter()
+                .map(|cell| cell.map_or('.', color_letter))
+                .collect();
+            seq.serialize_element(&row)?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DisplayBoard {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use crate::puzzles::format::parse_color;
+        use serde::de::Error;
+        let rows = Vec::<String>::deserialize(deserializer)?;
+        if rows.len() != HEIGHT as usize {
+            let expected = format!("{} rows", HEIGHT);
+            return Err(D::Error::invalid_length(rows.len(), &expected.as_str()));
+        }
+        let mut board = DisplayBoard::empty();
+        for (row, cells) in rows.iter().enumerate() {
+            if cells.chars().count() != WIDTH as usize {
+                let expected = format!("a row of {} cells", WIDTH);
+                return Err(D::Error::invalid_value(
+                    serde::de::Unexpected::Str(cells),
+                    &expected.as_str(),
+                ));
+            }
+            for (col, c) in cells.chars().enumerate() {
+                board.cells[row * WIDTH as usize + col] = match c {
+                    '.' => None,
+                    _ => Some(parse_color(&c.to_string()).ok_or_else(|| {
+                        D::Error::invalid_value(serde::de::Unexpected::Char(c), &"a color letter")
+                    })?),
+                };
+            }
+        }
+        Ok(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles::PIECES_49;
+
+    #[test]
+    fn try_with_piece_errors() {
+        let piece = |color, top_left| PlacedPiece {
+            piece: Piece::new()
+                .with_color(color)
+                .with_face(Face::A)
+                .with_orientation(Orientation::Up),
+            top_left,
+        };
+        let board = DisplayBoard::from_placed_piece_list(&*PIECES_49).unwrap();
+        assert_eq!(
+            board.try_with_piece(piece(Color::Yellow, 0)),
+            Err(PlacementError::DuplicateColor(Color::Yellow))
+        );
+        assert_eq!(
+            board.try_with_piece(piece(Color::Red, 9)),
+            Err(PlacementError::OutOfBounds { cell: 9 })
+        );
+        assert_eq!(
+            board.try_with_piece(piece(Color::Red, 3)),
+            Err(PlacementError::Overlap {
+                cell: 34,
+                color: Color::Blue
+            })
+        );
+        let with_red = board.try_with_piece(piece(Color::Red, 2)).unwrap();
+        assert_eq!(with_red, board.with_piece(piece(Color::Red, 2)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_board_and_solution() {
+        use crate::solver::{solve_with_options, SolveOptions, SolveOutcome};
+
+        let board = DisplayBoard::from_placed_piece_list(&*PIECES_49).unwrap();
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(
+            json,
+            r#"["..........","YY..B...G.","YY..B...GG","Y...B...G.","Y...BB...."]"#
+        );
+        assert_eq!(serde_json::from_str::<DisplayBoard>(&json).unwrap(), board);
+        assert!(serde_json::from_str::<DisplayBoard>(r#"["YY"]"#).is_err());
+
+        let outcome = solve_with_options::<DisplayBoard>(&*PIECES_49, &SolveOptions::new());
+        let json = serde_json::to_string(&outcome).unwrap();
+        assert!(json.starts_with(r#"{"solved":[{"piece":{"color":"yellow""#));
+        assert_eq!(
+            serde_json::from_str::<SolveOutcome>(&json).unwrap(),
+            outcome
+        );
+        let aborted = SolveOutcome::Aborted { nodes_explored: 3 };
+        assert_eq!(
+            serde_json::to_string(&aborted).unwrap(),
+            r#"{"aborted":{"nodes_explored":3}}"#
+        );
+    }
+}
@@ -16,4 +16,7 @@ pub struct DisplayBoardPlacementInfo {
     pub balls: [u8; 6],
     /// The representation of the piece as a bitfield.
     pub as_binary: u64,
+    /// The balls sticking up from the board rather than lying on it, as a bitfield of indices in
+    /// `balls`.
+    pub raised_balls: u8,
 }
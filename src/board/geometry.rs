@@ -0,0 +1,155 @@
+use crate::board::*;
+
+/// The size of a board, for boards other than the standard one.
+///
+/// The boards and most of the solver are specialized for the standard `WIDTH` x `HEIGHT` board.
+/// Other sizes, e.g. smaller practice boards, are solved by `solver::GeometrySolver`. The
+/// cells are numbered row by row from the top left, as on the standard board.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BoardGeometry {
+    width: u8,
+    height: u8,
+}
+
+impl BoardGeometry {
+    /// The standard board.
+    pub const STANDARD: BoardGeometry = BoardGeometry {
+        width: WIDTH,
+        height: HEIGHT,
+    };
+
+    /// A board of the given size, or None if it's empty or has more than 256 cells.
+    pub fn new(width: u8, height: u8) -> Option<Self> {
+        let num_cells = width as usize * height as usize;
+        if num_cells == 0 || num_cells > 256 {
+            None
+        } else {
+            Some(BoardGeometry { width, height })
+        }
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    pub fn num_cells(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// The cells covered by the piece, or None if it doesn't fit on the board.
+    pub fn piece_cells(&self, piece: PlacedPiece) -> Option<Vec<u8>> {
+        piece_cells(self.width, self.height, piece).map(Iterator::collect)
+    }
+}
+
+/// The cells covered by the piece on a board of the given size, or None if it doesn't fit.
+pub(crate) fn piece_cells(
+    width: u8,
+    height: u8,
+    piece: PlacedPiece,
+) -> Option<impl Iterator<Item = u8>> {
+    let info = get_placement_info(piece.piece);
+    // In usize, since the cells of the pieces that stick out of the board can be past 255.
+    let (width, height) = (width as usize, height as usize);
+    let (info_height, width_left) = (info.height as usize, info.width_left as usize);
+    let (row, col) = (
+        piece.top_left as usize / width,
+        piece.top_left as usize % width,
+    );
+    let fits = (piece.top_left as usize) < width * height
+        && row + info_height < height
+        && col + (info.width_right as usize) < width
+        && col >= width_left;
+    if !fits {
+        return None;
+    }
+    let cells = info.balls[..info.num_balls as usize]
+        .iter()
+        .map(move |&ball| {
+            // The balls are indexed on the standard board, relative to the top-left ball.
+            let shifted = ball as usize + width_left;
+            let (ball_row, ball_col) = (shifted / WIDTH as usize, shifted % WIDTH as usize);
+            ((row + ball_row) * width + col + ball_col - width_left) as u8
+        });
+    Some(cells)
+}
+
+impl Default for BoardGeometry {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_piece_cells() {
+        let geometry = BoardGeometry::STANDARD;
+        for top_left in 0..NUM_CELLS {
+            for byte in 0..80 {
+                let piece = PlacedPiece {
+                    piece: Piece::from_byte(byte),
+                    top_left,
+                };
+                let mask = geometry
+                    .piece_cells(piece)
+                    .map(|cells| cells.iter().fold(0u64, |acc, c| acc | 1 << c));
+                assert_eq!(mask, placement_mask(piece), "{:?}", piece);
+            }
+        }
+    }
+
+    #[test]
+    fn small_board() {
+        let geometry = BoardGeometry::new(4, 3).unwrap();
+        let piece = |orientation, top_left| PlacedPiece {
+            piece: Piece::new()
+                .with_color(Color::Yellow)
+                .with_face(Face::A)
+                .with_orientation(orientation),
+            top_left,
+        };
+        // The yellow piece fills the first row, and the cell below its right end.
+        assert_eq!(
+            geometry.piece_cells(piece(Orientation::Right, 0)),
+            Some(vec![0, 1, 2, 3, 7])
+        );
+        assert_eq!(geometry.piece_cells(piece(Orientation::Right, 1)), None);
+        assert_eq!(geometry.piece_cells(piece(Orientation::Up, 0)), None);
+        assert_eq!(geometry.piece_cells(piece(Orientation::Right, 8)), None);
+        assert_eq!(BoardGeometry::new(0, 5), None);
+        assert_eq!(BoardGeometry::new(20, 20), None);
+    }
+
+    #[test]
+    fn long_boards() {
+        // The pieces sticking out of these boards go past cell 255.
+        let set = PieceSet::standard();
+        for &(width, height) in [(1, 255), (255, 1), (128, 2), (2, 128), (51, 5)].iter() {
+            let geometry = BoardGeometry::new(width, height).unwrap();
+            for top_left in 0..=255 {
+                for byte in 0..80 {
+                    let piece = PlacedPiece {
+                        piece: Piece::from_byte(byte),
+                        top_left,
+                    };
+                    let cells = geometry.piece_cells(piece).map(|mut cells| {
+                        cells.sort_unstable();
+                        cells
+                    });
+                    let expected = set.piece_cells(&geometry, piece).map(|mut cells| {
+                        cells.sort_unstable();
+                        cells
+                    });
+                    assert_eq!(cells, expected, "{}x{} {:?}", width, height, piece);
+                }
+            }
+        }
+    }
+}
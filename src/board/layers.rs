@@ -0,0 +1,222 @@
+//! The pieces in 3D: which balls lie on the board, and which ones stick up.
+//!
+//! Each piece has a base line of balls lying on the board. The other balls are on its side:
+//! they stick up, one on face A and two on face B. Seen from above, every ball covers a cell,
+//! which is all the other boards model. `LayeredBoard` also tracks the layer of each ball, e.g.
+//! to draw the pieces in 3D, or for challenges where pieces are stacked.
+
+use crate::board::display_board::paint;
+use crate::board::*;
+use std::fmt;
+
+/// The height of a ball.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Layer {
+    /// Lying on the board.
+    Bottom,
+    /// Sticking up from the piece.
+    Top,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 2] = [Layer::Bottom, Layer::Top];
+}
+
+/// The cells covered by the balls of the piece, with their layer, or None if it doesn't fit on
+/// the board.
+pub fn ball_layers(piece: PlacedPiece) -> Option<Vec<(u8, Layer)>> {
+    let info = get_placement_info(piece.piece);
+    if piece.top_left >= NUM_CELLS || !is_valid_piece_placement(piece, info) {
+        return None;
+    }
+    let balls = (0..info.num_balls as usize).map(|i| {
+        let layer = if info.raised_balls & (1 << i) != 0 {
+            Layer::Top
+        } else {
+            Layer::Bottom
+        };
+        (piece.top_left + info.balls[i], layer)
+    });
+    Some(balls.collect())
+}
+
+/// The cells covered by the balls of the piece on each layer, as bitfields indexed by `Layer`,
+/// or None if it doesn't fit on the board.
+pub fn layer_masks(piece: PlacedPiece) -> Option<[u64; 2]> {
+    let mut masks = [0; 2];
+    for (cell, layer) in ball_layers(piece)? {
+        masks[layer as usize] |= 1 << cell;
+    }
+    Some(masks)
+}
+
+/// A board with two layers of cells: the balls lying on the board, and the ones sticking up.
+///
+/// A ball only conflicts with the balls on its layer, so a piece can stick up above a cell
+/// covered on the bottom layer. A board of the 2D game is flat: no cell is covered twice.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct LayeredBoard {
+    cells: [[Option<Color>; NUM_CELLS as usize]; 2],
+}
+
+impl LayeredBoard {
+    pub fn empty() -> Self {
+        LayeredBoard {
+            cells: [[None; NUM_CELLS as usize]; 2],
+        }
+    }
+
+    /// The board with the pieces, or why one of them can't be placed.
+    pub fn from_pieces(pieces: &[PlacedPiece]) -> Result<Self, PlacementError> {
+        pieces
+            .iter()
+            .try_fold(Self::empty(), |board, p| board.try_with_piece(*p))
+    }
+
+    /// The board with the piece added, or why it can't be placed: a ball overlaps another one on
+    /// the same layer.
+    pub fn try_with_piece(&self, piece: PlacedPiece) -> Result<Self, PlacementError> {
+        let color = piece.piece.color();
+        if self.cells.iter().any(|layer| layer.contains(&Some(color))) {
+            return Err(PlacementError::DuplicateColor(color));
+        }
+        let balls = ball_layers(piece).ok_or(PlacementError::OutOfBounds {
+            cell: piece.top_left,
+        })?;
+        let mut board = *self;
+        for (cell, layer) in balls {
+            let slot = &mut board.cells[layer as usize][cell as usize];
+            if let Some(color) = *slot {
+                return Err(PlacementError::Overlap { cell, color });
+            }
+            *slot = Some(color);
+        }
+        Ok(board)
+    }
+
+    /// The color of the ball on this cell and layer, if any.
+    pub fn cell(&self, cell: u8, layer: Layer) -> Option<Color> {
+        self.cells[layer as usize][cell as usize]
+    }
+
+    /// The cells covered on this layer, as a bitfield.
+    pub fn layer_mask(&self, layer: Layer) -> u64 {
+        self.cells[layer as usize]
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.is_some())
+            .fold(0, |acc, (i, _)| acc | 1 << i)
+    }
+
+    /// Whether no cell is covered on both layers, as on the board of the 2D game.
+    pub fn is_flat(&self) -> bool {
+        self.layer_mask(Layer::Bottom) & self.layer_mask(Layer::Top) == 0
+    }
+}
+
+impl Default for LayeredBoard {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+/// Like `DisplayBoard`, with `()` on the balls sticking up. A cell covered on both layers has
+/// the color of its top ball.
+impl fmt::Display for LayeredBoard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in 0..HEIGHT {
+            for col in 0..WIDTH {
+                let cell = row * WIDTH + col;
+                let text = match self.cell(cell, Layer::Top) {
+                    Some(color) => paint("()", Some(color)),
+                    None => paint("  ", self.cell(cell, Layer::Bottom)),
+                };
+                write!(f, "{}", text)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles::PIECES_117;
+    use crate::solver::solve;
+
+    #[test]
+    fn raised_balls() {
+        for top_left in 0..NUM_CELLS {
+            for byte in 0..80 {
+                let piece = PlacedPiece {
+                    piece: Piece::from_byte(byte),
+                    top_left,
+                };
+                let masks = layer_masks(piece);
+                assert_eq!(masks.map(|m| m[0] | m[1]), placement_mask(piece));
+                if let Some([bottom, top]) = masks {
+                    assert_eq!(bottom & top, 0);
+                    let expected = match piece.piece.face() {
+                        Face::A => 1,
+                        Face::B => 2,
+                    };
+                    assert_eq!(top.count_ones(), expected, "{:?}", piece);
+                }
+            }
+        }
+        // The pink piece on its A face, turned right: the ball under its base line sticks up.
+        let pink = PlacedPiece {
+            piece: Piece::new()
+                .with_color(Color::Pink)
+                .with_face(Face::A)
+                .with_orientation(Orientation::Right),
+            top_left: 0,
+        };
+        assert_eq!(layer_masks(pink), Some([0b1111, 1 << 12]));
+    }
+
+    #[test]
+    fn stacked_pieces() {
+        let solution = solve::<BinaryBoard>(&*PIECES_117).unwrap();
+        let board = LayeredBoard::from_pieces(&solution).unwrap();
+        assert!(board.is_flat());
+        let text = board.to_string();
+        assert_eq!(text.lines().count(), HEIGHT as usize);
+        assert_eq!(text.matches("()").count(), 14);
+        assert_eq!(
+            board.layer_mask(Layer::Bottom) | board.layer_mask(Layer::Top),
+            (1 << NUM_CELLS) - 1
+        );
+
+        let piece = |color, orientation, top_left| PlacedPiece {
+            piece: Piece::new()
+                .with_color(color)
+                .with_face(Face::A)
+                .with_orientation(orientation),
+            top_left,
+        };
+        // The raised ball of the pink piece is above the base line of the yellow one.
+        let pink = piece(Color::Pink, Orientation::Right, 0);
+        let yellow = piece(Color::Yellow, Orientation::Right, 11);
+        let stacked = LayeredBoard::from_pieces(&[pink, yellow]).unwrap();
+        assert!(!stacked.is_flat());
+        assert_eq!(stacked.cell(12, Layer::Top), Some(Color::Pink));
+        assert_eq!(stacked.cell(12, Layer::Bottom), Some(Color::Yellow));
+        assert_eq!(
+            stacked.try_with_piece(piece(Color::Red, Orientation::Right, 1)),
+            Err(PlacementError::Overlap {
+                cell: 1,
+                color: Color::Pink
+            })
+        );
+        assert_eq!(
+            stacked.try_with_piece(pink),
+            Err(PlacementError::DuplicateColor(Color::Pink))
+        );
+        assert_eq!(
+            LayeredBoard::empty().try_with_piece(piece(Color::Red, Orientation::Up, 9)),
+            Err(PlacementError::OutOfBounds { cell: 9 })
+        );
+    }
+}
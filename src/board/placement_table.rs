@@ -0,0 +1 @@
+include!(concat!(env!("OUT_DIR"), "/placement_table_gen.rs"));
@@ -0,0 +1,522 @@
+use crate::board::*;
+
+/// Model a board of `W` x `H` cells as a bitfield of empty cells, for boards too large for
+/// `BinaryBoard`.
+///
+/// The bitfield is a `u128`, so the board can have up to 128 cells. The cells are numbered row
+/// by row from the top left, as on the standard board, and the failure patterns are the ones of
+/// `BinaryBoard` with rows of `W` cells. `WideBinaryBoard<10, 5>` is the standard board.
+///
+/// Only the standard board implements `Board`, since the solver walks the placements precomputed
+/// for it and keeps the empty cells in a `u64`. Boards of other sizes have the same operations as
+/// inherent methods, and are solved with `GeometrySolver`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct WideBinaryBoard<const W: u8 = WIDTH, const H: u8 = HEIGHT> {
+    /// Bitfield of empty cells. Each bit corresponds to a specific cell, starting from the top
+    /// left and going row by row.
+    cells: u128,
+}
+
+// The inherent methods take precedence over the ones of the trait, so most of these forward to
+// the methods of any size below.
+impl Board for WideBinaryBoard {
+    #[inline]
+    fn can_place_piece(&self, piece: PlacedPiece) -> bool {
+        self.can_place_piece(piece)
+    }
+    #[inline]
+    fn with_piece(self, piece: PlacedPiece) -> Self {
+        self.with_piece(piece)
+    }
+    fn maybe_with_piece(&self, piece: PlacedPiece) -> Option<Self> {
+        self.maybe_with_piece(piece)
+    }
+    #[inline]
+    fn maybe_with_placement(&self, placement: &Placement, _top_left: u8) -> Option<Self> {
+        let mask = placement.mask as u128;
+        if self.cells & mask != 0 {
+            None
+        } else {
+            Some(WideBinaryBoard {
+                cells: self.cells | mask,
+            })
+        }
+    }
+    fn empty() -> Self {
+        Self::empty()
+    }
+    #[inline]
+    fn empty_cells(&self) -> u64 {
+        self.wide_empty_cells() as u64
+    }
+    #[inline]
+    fn is_cell_empty(&self, index: u8) -> bool {
+        self.is_cell_empty(index)
+    }
+    #[inline]
+    fn check_common_failures(&self) -> bool {
+        self.check_common_failures()
+    }
+    fn common_failure(&self) -> Option<FailurePattern> {
+        self.common_failure()
+    }
+    #[inline]
+    fn first_empty_cell(&self, lower_bound: u8) -> Option<u8> {
+        self.first_empty_cell(lower_bound)
+    }
+    fn try_from_placed_piece_list(pieces: &[PlacedPiece]) -> Result<Self, PlacementError> {
+        Self::try_from_placed_piece_list(pieces)
+    }
+}
+
+impl<const W: u8, const H: u8> Default for WideBinaryBoard<W, H> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<const W: u8, const H: u8> WideBinaryBoard<W, H> {
+    /// The number of cells of the board. Using a board of more than 128 cells fails to compile.
+    pub const NUM_CELLS: u8 = {
+        let num_cells = W as usize * H as usize;
+        assert!(num_cells > 0 && num_cells <= 128);
+        num_cells as u8
+    };
+
+    /// The board with no piece on it.
+    pub fn empty() -> Self {
+        WideBinaryBoard {
+            // Set the cells after the board to full.
+            cells: (!0u128).checked_shl(Self::NUM_CELLS as u32).unwrap_or(0),
+        }
+    }
+
+    /// Whether the piece fits on the board, without overlapping the pieces already there.
+    #[inline]
+    pub fn can_place_piece(&self, piece: PlacedPiece) -> bool {
+        Self::piece_mask(piece).is_some_and(|mask| self.cells & mask == 0)
+    }
+
+    /// The board with the piece added. The piece must fit, see `can_place_piece`.
+    #[inline]
+    pub fn with_piece(mut self, piece: PlacedPiece) -> Self {
+        self.cells |= Self::piece_mask(piece).unwrap_or(0);
+        self
+    }
+
+    /// The board with the piece added, or None if it doesn't fit.
+    pub fn maybe_with_piece(&self, piece: PlacedPiece) -> Option<Self> {
+        let mask = Self::piece_mask(piece)?;
+        if self.cells & mask != 0 {
+            None
+        } else {
+            Some(WideBinaryBoard {
+                cells: self.cells | mask,
+            })
+        }
+    }
+
+    /// The board with the pieces, or why one of them can't be placed.
+    pub fn try_from_placed_piece_list(pieces: &[PlacedPiece]) -> Result<Self, PlacementError> {
+        let mut board = Self::empty();
+        for (i, p) in pieces.iter().enumerate() {
+            Self::check_placement(&pieces[..i], *p)?;
+            board = board.with_piece(*p);
+        }
+        Ok(board)
+    }
+
+    #[inline]
+    pub fn is_cell_empty(&self, index: u8) -> bool {
+        index < Self::NUM_CELLS && self.cells & (1 << index) == 0
+    }
+
+    /// The first empty cell from `lower_bound` on, if any.
+    #[inline]
+    pub fn first_empty_cell(&self, lower_bound: u8) -> Option<u8> {
+        let empty = !self.cells & (!0u128).checked_shl(lower_bound as u32)?;
+        let first_empty = empty.trailing_zeros() as u8;
+        if first_empty >= Self::NUM_CELLS {
+            None
+        } else {
+            Some(first_empty)
+        }
+    }
+
+    /// Check for the failure patterns of `Board::check_common_failures`.
+    #[inline]
+    pub fn check_common_failures(&self) -> bool {
+        self.failure_masks().iter().fold(0, |acc, mask| acc | mask) != 0
+    }
+
+    /// Like `check_common_failures`, but also says which pattern was found.
+    pub fn common_failure(&self) -> Option<FailurePattern> {
+        let masks = self.failure_masks();
+        FailurePattern::ALL
+            .iter()
+            .zip(masks.iter())
+            .find(|(_, &mask)| mask != 0)
+            .map(|(&pattern, _)| pattern)
+    }
+
+    /// Whether this is the standard board, whose placements are precomputed.
+    const IS_STANDARD: bool = W == WIDTH && H == HEIGHT;
+
+    /// The size of the board.
+    pub fn geometry() -> BoardGeometry {
+        BoardGeometry::new(W, H).unwrap()
+    }
+
+    /// The bitfield of empty cells of the whole board.
+    #[inline]
+    pub fn wide_empty_cells(&self) -> u128 {
+        !self.cells
+    }
+
+    /// The cells covered by the piece, or None if it doesn't fit on the board.
+    #[inline]
+    fn piece_mask(piece: PlacedPiece) -> Option<u128> {
+        if Self::IS_STANDARD {
+            let info = get_placement_info(piece.piece);
+            if piece.top_left >= NUM_CELLS || !is_valid_piece_placement(piece, info) {
+                return None;
+            }
+            return Some((info.as_binary as u128) << piece.top_left);
+        }
+        geometry::piece_cells(W, H, piece).map(|cells| cells.fold(0, |acc, c| acc | 1 << c))
+    }
+
+    /// Check that `piece` fits on the board next to the `placed` pieces.
+    fn check_placement(placed: &[PlacedPiece], piece: PlacedPiece) -> Result<(), PlacementError> {
+        let mask = Self::piece_mask(piece).ok_or(PlacementError::OutOfBounds {
+            cell: piece.top_left,
+        })?;
+        for other in placed {
+            if other.piece.color() == piece.piece.color() {
+                return Err(PlacementError::DuplicateColor(piece.piece.color()));
+            }
+            let overlap = Self::piece_mask(*other).unwrap_or(0) & mask;
+            if overlap != 0 {
+                return Err(PlacementError::Overlap {
+                    cell: overlap.trailing_zeros() as u8,
+                    color: other.piece.color(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn failure_masks(&self) -> [u128; FailurePattern::COUNT] {
+        failure_masks(self.cells, W)
+    }
+}
+
+/// Check for the failure patterns of `Board::check_common_failures` on a board `width` cells
+/// wide, whatever its height. `full_cells` is the bitfield of the full cells, with the cells past
+/// the end of the board full, like in `WideBinaryBoard`.
+///
+/// This is for the boards whose size is only known at run time, see `solver::GeometrySolver`.
+#[inline]
+pub(crate) fn has_common_failure(full_cells: u128, width: u8) -> bool {
+    failure_masks(full_cells, width)
+        .iter()
+        .fold(0, |acc, mask| acc | mask)
+        != 0
+}
+
+/// Look for the failure patterns, in the order of `FailurePattern::ALL`, on a board `width` cells
+/// wide with the full cells `n`. Each mask is non-zero iff the pattern was found.
+///
+/// The patterns are the ones of `BinaryBoard`, with the shifts computed from the width of the
+/// board. The bitfield is shifted as a whole, so a pattern may span both halves of it.
+#[inline]
+fn failure_masks(n: u128, width: u8) -> [u128; FailurePattern::COUNT] {
+    // Shift the bitfield so that each bit holds the cell `rows` rows above and `cols`
+    // columns on the left of it.
+    let shift = |rows: i32, cols: i32| (rows * width as i32 + cols) as u32;
+    // The cells shifted in from outside the board are neither full nor empty, so that no
+    // pattern is found there.
+    let full = |rows, cols| n.checked_shl(shift(rows, cols)).unwrap_or(0);
+    let empty = |rows, cols| (!n).checked_shl(shift(rows, cols)).unwrap_or(0);
+    // Prepare the following pattern:
+    //  #
+    // #.
+    let base_pattern = n & full(1, -1) & empty(1, 0);
+    // Look for the following pattern:
+    //  #
+    // #.#
+    //  #
+    let single_hole = base_pattern & full(2, 0) & full(1, 1);
+    // Prepare the following pattern:
+    //  ##
+    // #..
+    //  #
+    let base_double_pattern = base_pattern & full(2, 0) & empty(1, 1) & full(0, 1);
+    // Look for the following pattern:
+    //  ##
+    // #..#
+    //  ##
+    let double_hole = base_double_pattern & full(1, 2) & full(2, 1);
+    // Look for the following pattern:
+    //  #
+    // #.#
+    // #.#
+    //  #
+    let double_hole_vertical =
+        base_pattern & full(1, 1) & empty(2, 0) & full(2, -1) & full(2, 1) & full(3, 0);
+    // Look for the following pattern:
+    //  ###
+    // #...#
+    //  ###
+    let triple_hole =
+        base_double_pattern & empty(1, 2) & full(2, 1) & full(0, 2) & full(2, 2) & full(1, 3);
+    // Look for the following pattern:
+    //  ##
+    // #..#
+    //  #.#
+    //   #
+    let triple_hole_l =
+        base_double_pattern & full(1, 2) & empty(2, 1) & full(3, 1) & full(2, 2) & full(1, 3);
+    // Look for the following pattern:
+    //  ##
+    // #..#
+    // #.#
+    //  #
+    let triple_hole_l2 = base_pattern
+        & full(0, 1)
+        & empty(1, 1)
+        & full(1, 2)
+        & full(2, -1)
+        & empty(2, 0)
+        & full(2, 1)
+        & full(3, 0);
+    // Look for the following pattern:
+    //  #
+    // #.#
+    // #..#
+    //  ##
+    let triple_hole_l3 = base_pattern
+        & full(1, 1)
+        & full(2, -1)
+        & empty(2, 0)
+        & empty(2, 1)
+        & full(2, 2)
+        & full(3, 0)
+        & full(3, 1);
+    // Look for the following pattern:
+    //   #
+    //  #.#
+    // #..#
+    //  ##
+    let triple_hole_l4 = base_pattern
+        & full(1, 1)
+        & full(2, -2)
+        & empty(2, -1)
+        & empty(2, 0)
+        & full(2, 1)
+        & full(3, -1)
+        & full(3, 0);
+    [
+        single_hole,
+        double_hole,
+        double_hole_vertical,
+        triple_hole,
+        triple_hole_l,
+        triple_hole_l2,
+        triple_hole_l3,
+        triple_hole_l4,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::Rng;
+
+    fn random_piece(rng: &mut Rng, num_cells: u8) -> PlacedPiece {
+        PlacedPiece {
+            piece: Piece::from_byte(rng.below(80) as u8),
+            top_left: rng.below(num_cells as u64) as u8,
+        }
+    }
+
+    /// The sizes of the connected regions of empty cells, without wrapping around the rows.
+    fn region_sizes<const W: u8, const H: u8>(board: &WideBinaryBoard<W, H>) -> Vec<usize> {
+        let mut seen = vec![false; WideBinaryBoard::<W, H>::NUM_CELLS as usize];
+        let mut sizes = Vec::new();
+        for start in 0..WideBinaryBoard::<W, H>::NUM_CELLS {
+            if seen[start as usize] || !board.is_cell_empty(start) {
+                continue;
+            }
+            seen[start as usize] = true;
+            let mut stack = vec![start];
+            let mut size = 0;
+            while let Some(cell) = stack.pop() {
+                size += 1;
+                let (row, col) = (cell / W, cell % W);
+                let neighbors = [
+                    (row > 0).then(|| cell - W),
+                    (row + 1 < H).then(|| cell + W),
+                    (col > 0).then(|| cell - 1),
+                    (col + 1 < W).then(|| cell + 1),
+                ];
+                for next in neighbors.iter().flatten() {
+                    if board.is_cell_empty(*next) && !seen[*next as usize] {
+                        seen[*next as usize] = true;
+                        stack.push(*next);
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+        sizes
+    }
+
+    #[test]
+    fn standard_board_random_placements() {
+        let mut rng = Rng::new(23);
+        for _ in 0..200 {
+            let mut display = DisplayBoard::empty();
+            let mut binary = BinaryBoard::empty();
+            let mut wide = WideBinaryBoard::<WIDTH, HEIGHT>::empty();
+            for _ in 0..40 {
+                let piece = random_piece(&mut rng, NUM_CELLS);
+                let can_place = display.can_place_piece(piece);
+                assert_eq!(wide.can_place_piece(piece), can_place, "{:?}", piece);
+                assert_eq!(wide.maybe_with_piece(piece).is_some(), can_place);
+                if !can_place {
+                    continue;
+                }
+                display = display.with_piece(piece);
+                binary = binary.with_piece(piece);
+                wide = wide.with_piece(piece);
+                assert_eq!(wide.empty_cells(), display.empty_cells());
+                assert_eq!(wide.wide_empty_cells() as u64, display.empty_cells());
+                for cell in 0..NUM_CELLS + 2 {
+                    assert_eq!(wide.is_cell_empty(cell), display.is_cell_empty(cell));
+                }
+                for lower_bound in 0..NUM_CELLS {
+                    assert_eq!(
+                        wide.first_empty_cell(lower_bound),
+                        display.first_empty_cell(lower_bound)
+                    );
+                }
+                assert_eq!(wide.common_failure(), binary.common_failure());
+                if wide.check_common_failures() {
+                    assert!(region_sizes(&wide).iter().any(|&size| size < 4));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn holes_across_words() {
+        type Board = WideBinaryBoard<12, 7>;
+        let with_holes = |holes: &[u8]| Board {
+            cells: holes.iter().fold(!0, |cells, hole| cells & !(1 << hole)),
+        };
+        // Cell 64 is on the 6th row, in the 5th column.
+        assert_eq!(
+            with_holes(&[64]).common_failure(),
+            Some(FailurePattern::SingleHole)
+        );
+        assert_eq!(
+            with_holes(&[63, 64]).common_failure(),
+            Some(FailurePattern::DoubleHole)
+        );
+        assert_eq!(
+            with_holes(&[52, 64]).common_failure(),
+            Some(FailurePattern::DoubleHoleVertical)
+        );
+        assert_eq!(
+            with_holes(&[62, 63, 64]).common_failure(),
+            Some(FailurePattern::TripleHole)
+        );
+        assert_eq!(
+            with_holes(&[52, 53, 65]).common_failure(),
+            Some(FailurePattern::TripleHoleL3)
+        );
+        assert_eq!(with_holes(&[52, 53, 64, 65]).common_failure(), None);
+        // A hole in the top row is not looked for.
+        assert_eq!(with_holes(&[5]).common_failure(), None);
+        assert_eq!(with_holes(&[64]).first_empty_cell(0), Some(64));
+        assert_eq!(with_holes(&[64]).first_empty_cell(65), None);
+    }
+
+    #[test]
+    fn wide_board_random_placements() {
+        type Board = WideBinaryBoard<12, 7>;
+        let geometry = Board::geometry();
+        let mut rng = Rng::new(23);
+        let mut failures = 0;
+        for _ in 0..200 {
+            let mut board = Board::empty();
+            let mut cells = vec![false; geometry.num_cells()];
+            for _ in 0..60 {
+                let piece = random_piece(&mut rng, Board::NUM_CELLS);
+                let piece_cells = geometry.piece_cells(piece);
+                let fits = piece_cells
+                    .as_ref()
+                    .is_some_and(|c| c.iter().all(|&cell| !cells[cell as usize]));
+                assert_eq!(board.can_place_piece(piece), fits, "{:?}", piece);
+                if !fits {
+                    continue;
+                }
+                for cell in piece_cells.unwrap() {
+                    cells[cell as usize] = true;
+                }
+                board = board.with_piece(piece);
+                for (cell, &full) in cells.iter().enumerate() {
+                    assert_eq!(board.is_cell_empty(cell as u8), !full);
+                }
+                assert_eq!(
+                    board.first_empty_cell(0),
+                    cells.iter().position(|&full| !full).map(|c| c as u8)
+                );
+                // The failures are holes too small for any piece.
+                if board.check_common_failures() {
+                    failures += 1;
+                    assert!(region_sizes(&board).iter().any(|&size| size < 4));
+                }
+            }
+        }
+        assert!(failures > 0);
+    }
+
+    #[test]
+    fn fill_practice_board() {
+        use crate::solver::{GeometrySolver, Solver};
+        type Board = WideBinaryBoard<5, 4>;
+        let colors = [
+            Color::Yellow,
+            Color::Orange,
+            Color::Red,
+            Color::Pink,
+            Color::LightGreen,
+            Color::Green,
+        ];
+        let solver = GeometrySolver::new(Board::geometry()).with_colors(&colors);
+        let solutions = solver.solve_all(&[]);
+        assert!(!solutions.is_empty());
+        for solution in &solutions {
+            let board = Board::try_from_placed_piece_list(solution).unwrap();
+            assert_eq!(board.first_empty_cell(0), None);
+            // No partial board of a solution is a dead end.
+            for placed in 0..solution.len() {
+                let partial = Board::try_from_placed_piece_list(&solution[..placed]).unwrap();
+                assert_eq!(partial.common_failure(), None, "{:?}", &solution[..placed]);
+            }
+        }
+    }
+
+    #[test]
+    fn solve_standard_board() {
+        use crate::puzzles::PIECES_117;
+        use crate::solver::solve;
+        assert_eq!(
+            solve::<WideBinaryBoard>(&*PIECES_117),
+            solve::<BinaryBoard>(&*PIECES_117)
+        );
+    }
+}
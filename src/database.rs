@@ -0,0 +1,271 @@
+//! A database of the solutions of the empty board, to answer queries without searching.
+//!
+//! The empty board has a finite number of solutions, so they can all be found once and saved.
+//! A puzzle is then solved by keeping the solutions that have all the given pieces, comparing
+//! the cells covered by each color.
+//!
+//! The file is a sequence of solutions of `RECORD_SIZE` bytes each. A solution is its 10 pieces
+//! in the order of the colors, each one stored as its `Piece::as_byte` followed by its top-left
+//! cell.
+
+use crate::board::{placement_mask, BinaryBoard, Board};
+use crate::pieces::*;
+use crate::solver::{solve_all_parallel, ColorSet, Solver};
+use std::io::{self, Read, Write};
+
+/// The number of bytes of a solution in the file.
+pub const RECORD_SIZE: usize = 20;
+
+/// The number of piece bytes: 10 colors, with 2 faces and 4 orientations each.
+const NUM_PIECE_BYTES: u8 = 80;
+
+/// The solutions of the empty board.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SolutionDatabase {
+    /// The pieces of each solution, in the order of the colors.
+    solutions: Vec<[PlacedPiece; 10]>,
+    /// The cells covered by each color, for each solution.
+    masks: Vec<[u64; 10]>,
+}
+
+impl SolutionDatabase {
+    /// Find every solution of the empty board, using `num_threads` threads.
+    pub fn build(num_threads: usize) -> Self {
+        let mut database =
+            Self::from_solutions(solve_all_parallel::<BinaryBoard>(&[], num_threads));
+        // The threads find the solutions in any order: sort them so that the file is always the
+        // same.
+        let mut records: Vec<[u8; RECORD_SIZE]> =
+            database.solutions.iter().map(to_record).collect();
+        records.sort_unstable();
+        database.solutions = records.iter().map(|r| from_record(r).unwrap()).collect();
+        database.masks = database.solutions.iter().map(masks).collect();
+        database
+    }
+
+    /// A database of the given solutions. Each solution must have 10 pieces covering the board.
+    pub fn from_solutions<I>(solutions: I) -> Self
+    where
+        I: IntoIterator<Item = Vec<PlacedPiece>>,
+    {
+        let solutions: Vec<[PlacedPiece; 10]> = solutions
+            .into_iter()
+            .map(|s| {
+                let mut pieces = [s[0]; 10];
+                for p in s {
+                    pieces[p.piece.color() as usize] = p;
+                }
+                pieces
+            })
+            .collect();
+        let masks = solutions.iter().map(masks).collect();
+        SolutionDatabase { solutions, masks }
+    }
+
+    /// Read a database written by `write`.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        if bytes.len() % RECORD_SIZE != 0 {
+            return Err(invalid_data("the file ends in the middle of a solution"));
+        }
+        let solutions = bytes
+            .chunks(RECORD_SIZE)
+            .enumerate()
+            .map(|(i, record)| {
+                from_record(record).ok_or_else(|| invalid_data(&format!("invalid solution {}", i)))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let masks = solutions.iter().map(masks).collect();
+        Ok(SolutionDatabase { solutions, masks })
+    }
+
+    /// Write the database in its binary format.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for solution in &self.solutions {
+            writer.write_all(&to_record(solution))?;
+        }
+        writer.flush()
+    }
+
+    /// The number of solutions in the database.
+    pub fn len(&self) -> usize {
+        self.solutions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.solutions.is_empty()
+    }
+
+    /// The solutions having all the given `pieces`, in the order of the database.
+    ///
+    /// Like the solver, each solution starts with the given pieces, followed by the others in
+    /// the order of the colors.
+    pub fn matching<'a>(
+        &'a self,
+        pieces: &'a [PlacedPiece],
+    ) -> impl Iterator<Item = Vec<PlacedPiece>> + 'a {
+        // Invalid pieces match no solution.
+        let valid = BinaryBoard::try_from_placed_piece_list(pieces).is_ok();
+        let given: Vec<(usize, u64)> = pieces
+            .iter()
+            .map(|p| (p.piece.color() as usize, placement_mask(*p).unwrap_or(0)))
+            .collect();
+        let given_colors =
+            ColorSet::from_colors(&pieces.iter().map(|p| p.piece.color()).collect::<Vec<_>>());
+        self.masks
+            .iter()
+            .zip(self.solutions.iter())
+            .filter(move |(masks, _)| {
+                valid && given.iter().all(|&(color, mask)| masks[color] == mask)
+            })
+            .map(move |(_, solution)| {
+                let mut solution_pieces = pieces.to_vec();
+                solution_pieces.extend(
+                    solution
+                        .iter()
+                        .filter(|p| !given_colors.contains(p.piece.color())),
+                );
+                solution_pieces
+            })
+    }
+
+    /// Check whether the puzzle has exactly one solution.
+    pub fn is_unique(&self, pieces: &[PlacedPiece]) -> bool {
+        self.count_solutions(pieces, Some(2)) == 1
+    }
+}
+
+impl Solver for SolutionDatabase {
+    fn solve(&self, pieces: &[PlacedPiece]) -> Option<Vec<PlacedPiece>> {
+        self.matching(pieces).next()
+    }
+
+    fn solve_all(&self, pieces: &[PlacedPiece]) -> Vec<Vec<PlacedPiece>> {
+        self.matching(pieces).collect()
+    }
+
+    fn count_solutions(&self, pieces: &[PlacedPiece], limit: Option<u64>) -> u64 {
+        let limit = limit.unwrap_or(u64::MAX);
+        self.matching(pieces).take(limit as usize).count() as u64
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn masks(solution: &[PlacedPiece; 10]) -> [u64; 10] {
+    let mut masks = [0; 10];
+    for (mask, p) in masks.iter_mut().zip(solution.iter()) {
+        *mask = placement_mask(*p).unwrap();
+    }
+    masks
+}
+
+fn to_record(solution: &[PlacedPiece; 10]) -> [u8; RECORD_SIZE] {
+    let mut record = [0; RECORD_SIZE];
+    for (bytes, p) in record.chunks_mut(2).zip(solution.iter()) {
+        bytes[0] = p.piece.as_byte();
+        bytes[1] = p.top_left;
+    }
+    record
+}
+
+/// The solution stored in the record, or None if it isn't one: the pieces must be in the order
+/// of the colors and fill the board.
+fn from_record(record: &[u8]) -> Option<[PlacedPiece; 10]> {
+    let mut solution = [PlacedPiece {
+        piece: Piece::from_byte(0),
+        top_left: 0,
+    }; 10];
+    for (i, (p, bytes)) in solution.iter_mut().zip(record.chunks(2)).enumerate() {
+        if bytes[0] >= NUM_PIECE_BYTES {
+            return None;
+        }
+        *p = PlacedPiece {
+            piece: Piece::from_byte(bytes[0]),
+            top_left: bytes[1],
+        };
+        if p.piece.color() as usize != i {
+            return None;
+        }
+    }
+    let board = BinaryBoard::try_from_placed_piece_list(&solution).ok()?;
+    match board.first_empty_cell(0) {
+        Some(_) => None,
+        None => Some(solution),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles::*;
+    use crate::solver::{count_solutions, solve, solve_all};
+
+    /// The solutions of 3 pieces of a solution of puzzle 117, as a small database.
+    fn small_database() -> (Vec<PlacedPiece>, SolutionDatabase) {
+        let solution = solve::<BinaryBoard>(&*PIECES_117).unwrap();
+        let pieces = solution[2..5].to_vec();
+        let database = SolutionDatabase::from_solutions(solve_all::<BinaryBoard>(&pieces));
+        (pieces, database)
+    }
+
+    /// The solutions with each one sorted by cell, in a fixed order.
+    fn normalized(mut solutions: Vec<Vec<PlacedPiece>>) -> Vec<Vec<PlacedPiece>> {
+        for s in solutions.iter_mut() {
+            s.sort_by_key(|p| p.top_left);
+        }
+        solutions.sort_by_key(|s| s.iter().map(|p| p.piece.as_byte()).collect::<Vec<_>>());
+        solutions
+    }
+
+    #[test]
+    fn lookup_matches_solver() {
+        let (pieces, database) = small_database();
+        assert!(database.len() > 1);
+        let first = database.solve(&pieces).unwrap();
+        assert_eq!(first[..pieces.len()], pieces[..]);
+        assert_eq!(first.len(), 10);
+        // Adding each one of the pieces of a solution.
+        for &extra in &first[pieces.len()..] {
+            let mut given = pieces.clone();
+            given.push(extra);
+            assert_eq!(
+                normalized(database.solve_all(&given)),
+                normalized(solve_all::<BinaryBoard>(&given))
+            );
+            assert_eq!(
+                database.count_solutions(&given, None),
+                count_solutions::<BinaryBoard>(&given, None)
+            );
+        }
+        assert_eq!(
+            database.count_solutions(&pieces, None),
+            database.len() as u64
+        );
+        assert_eq!(database.count_solutions(&pieces, Some(1)), 1);
+        assert_eq!(database.count_solutions(&[], None), database.len() as u64);
+        assert!(!database.is_unique(&pieces));
+        assert!(database.is_unique(&first));
+        // Overlapping pieces.
+        assert_eq!(database.solve(&[pieces[0], pieces[0]]), None);
+    }
+
+    #[test]
+    fn read_write() {
+        let (_, database) = small_database();
+        let mut bytes = Vec::new();
+        database.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), database.len() * RECORD_SIZE);
+        assert_eq!(SolutionDatabase::read(&bytes[..]).unwrap(), database);
+        assert!(SolutionDatabase::read(&bytes[1..]).is_err());
+        // Swap the first two pieces.
+        bytes.swap(0, 2);
+        bytes.swap(1, 3);
+        assert!(SolutionDatabase::read(&bytes[..]).is_err());
+        bytes[0] = NUM_PIECE_BYTES;
+        assert!(SolutionDatabase::read(&bytes[..]).is_err());
+    }
+}
@@ -0,0 +1,297 @@
+//! Generation of new puzzles with a unique solution.
+//!
+//! A puzzle starts as a random full board. Pieces are then removed one at a time, in a random
+//! order, as long as the puzzle stays unique.
+
+use crate::board::{placements, BinaryBoard, Placement, NUM_CELLS};
+use crate::pieces::*;
+use crate::puzzles::Level;
+use crate::rating::{rate, Rating};
+use crate::solver::{
+    for_each_solution_with_shapes, is_unique, Shapes, StandardShapes, TenPieceFacePolicy,
+    COLOR_LIST,
+};
+
+/// A small seedable random number generator (SplitMix64), so that generated puzzles can be
+/// reproduced from their seed.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A random number in `0..n`. `n` must not be 0.
+    pub fn below(&mut self, n: u64) -> u64 {
+        // The bias is negligible for the small ranges used here.
+        self.next_u64() % n
+    }
+
+    /// Shuffle the slice, with Fisher-Yates.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+/// Configuration of the generator.
+#[derive(Debug, Clone)]
+pub struct GeneratorOptions {
+    /// The seed of the random number generator.
+    pub seed: u64,
+    /// The number of pieces given in the puzzle. By default, remove pieces until none can be
+    /// removed without losing uniqueness.
+    pub num_given: Option<usize>,
+    /// The level to aim for, as rated by `Rating::level`.
+    pub level: Option<Level>,
+    /// How many full boards to try before giving up on the targets.
+    pub max_attempts: u32,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        GeneratorOptions {
+            seed: 0,
+            num_given: None,
+            level: None,
+            max_attempts: 100,
+        }
+    }
+}
+
+impl GeneratorOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_num_given(mut self, num_given: usize) -> Self {
+        self.num_given = Some(num_given);
+        self
+    }
+
+    pub fn with_level(mut self, level: Level) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+}
+
+/// A generated puzzle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedPuzzle {
+    /// The pieces given at the start.
+    pub pieces: Vec<PlacedPiece>,
+    /// The only solution of the puzzle.
+    pub solution: Vec<PlacedPiece>,
+    /// How hard the puzzle is, see `rating::rate`.
+    pub rating: Rating,
+}
+
+/// The standard pieces, with their placements in a random order for `random_solution`.
+///
+/// The search goes through the colors in the order of `COLOR_LIST`, so they are shuffled too:
+/// for the color `c`, the search places the piece `colors[c]`.
+struct ShuffledShapes {
+    colors: [Color; 10],
+    /// The placements of `colors[c]`, indexed by top-left cell, then by `c`.
+    placements: Vec<Vec<Vec<Placement>>>,
+}
+
+impl ShuffledShapes {
+    fn new(rng: &mut Rng) -> Self {
+        let mut colors = COLOR_LIST;
+        rng.shuffle(&mut colors);
+        let placements = (0..NUM_CELLS)
+            .map(|top_left| {
+                colors
+                    .iter()
+                    .map(|&color| {
+                        let mut placements = placements(top_left, color).to_vec();
+                        rng.shuffle(&mut placements);
+                        placements
+                    })
+                    .collect()
+            })
+            .collect();
+        ShuffledShapes { colors, placements }
+    }
+
+    /// The piece that the search places for the color of `piece`.
+    fn actual_piece(&self, piece: Piece) -> Piece {
+        piece.with_color(self.colors[piece.color() as usize])
+    }
+}
+
+impl Shapes for &ShuffledShapes {
+    fn placements(&self, top_left: u8, color: Color) -> &[Placement] {
+        &self.placements[top_left as usize][color as usize]
+    }
+    fn num_balls(&self, piece: Piece) -> u8 {
+        StandardShapes.num_balls(self.actual_piece(piece))
+    }
+    fn placement(&self, piece: PlacedPiece) -> Option<Placement> {
+        StandardShapes.placement(PlacedPiece {
+            piece: self.actual_piece(piece.piece),
+            ..piece
+        })
+    }
+}
+
+/// A random full board.
+pub fn random_solution(rng: &mut Rng) -> Vec<PlacedPiece> {
+    let shapes = ShuffledShapes::new(rng);
+    let mut solution = Vec::new();
+    for_each_solution_with_shapes::<BinaryBoard, TenPieceFacePolicy, _, _>(
+        &[],
+        &shapes,
+        |pieces| {
+            solution = pieces.to_vec();
+            false
+        },
+    );
+    debug_assert_eq!(solution.len(), 10);
+    solution
+}
+
+/// Remove pieces from the solution in a random order, as long as the puzzle stays unique, and
+/// until there are `num_given` pieces left if given.
+///
+/// The pieces left are in the order of the solution.
+pub fn remove_pieces(
+    solution: &[PlacedPiece],
+    num_given: Option<usize>,
+    rng: &mut Rng,
+) -> Vec<PlacedPiece> {
+    let mut given = solution.to_vec();
+    let mut order = solution.to_vec();
+    rng.shuffle(&mut order);
+    for piece in order {
+        if num_given.is_some_and(|n| given.len() <= n) {
+            break;
+        }
+        let without: Vec<PlacedPiece> = given.iter().cloned().filter(|p| *p != piece).collect();
+        if is_unique::<BinaryBoard>(&without) {
+            given = without;
+        }
+    }
+    given
+}
+
+/// Generate a puzzle with a unique solution, meeting the targets of the options.
+/// Returns None if no puzzle was found within `max_attempts`.
+pub fn generate(options: &GeneratorOptions) -> Option<GeneratedPuzzle> {
+    let mut rng = Rng::new(options.seed);
+    for _ in 0..options.max_attempts {
+        let solution = random_solution(&mut rng);
+        let pieces = remove_pieces(&solution, options.num_given, &mut rng);
+        if options.num_given.is_some_and(|n| pieces.len() != n) {
+            continue;
+        }
+        let rating = match rate(&pieces) {
+            Ok(Some(rating)) => rating,
+            _ => continue,
+        };
+        if options.level.is_some_and(|level| rating.level() != level) {
+            continue;
+        }
+        return Some(GeneratedPuzzle {
+            pieces,
+            solution,
+            rating,
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::solver::solve;
+
+    #[test]
+    fn random_solution_fills_board() {
+        let mut rng = Rng::new(42);
+        let solution = random_solution(&mut rng);
+        assert_eq!(solution.len(), 10);
+        let board = BinaryBoard::from_placed_piece_list(&solution).unwrap();
+        assert_eq!(board.first_empty_cell(0), None);
+        assert_ne!(random_solution(&mut rng), solution);
+    }
+
+    #[test]
+    fn generate_unique() {
+        let options = GeneratorOptions::new().with_seed(7).with_num_given(4);
+        let puzzle = generate(&options).unwrap();
+        assert_eq!(puzzle.pieces.len(), 4);
+        assert!(is_unique::<BinaryBoard>(&puzzle.pieces));
+        let mut solution = solve::<BinaryBoard>(&puzzle.pieces).unwrap();
+        let mut expected = puzzle.solution.clone();
+        solution.sort_by_key(|p| p.top_left);
+        expected.sort_by_key(|p| p.top_left);
+        assert_eq!(solution, expected);
+        // The same seed gives the same puzzle.
+        assert_eq!(generate(&options), Some(puzzle));
+    }
+
+    #[test]
+    fn generate_minimal() {
+        let puzzle = generate(&GeneratorOptions::new().with_seed(3)).unwrap();
+        assert!(is_unique::<BinaryBoard>(&puzzle.pieces));
+        for piece in &puzzle.pieces {
+            let without: Vec<PlacedPiece> = puzzle
+                .pieces
+                .iter()
+                .cloned()
+                .filter(|p| p != piece)
+                .collect();
+            assert!(!is_unique::<BinaryBoard>(&without));
+        }
+    }
+
+    #[test]
+    fn generate_impossible_targets() {
+        let options = GeneratorOptions::new()
+            .with_num_given(0)
+            .with_max_attempts(2);
+        assert_eq!(generate(&options), None);
+        let options = GeneratorOptions::new()
+            .with_num_given(8)
+            .with_level(Level::Wizard)
+            .with_max_attempts(2);
+        assert_eq!(generate(&options), None);
+    }
+
+    #[test]
+    fn generate_level() {
+        let options = GeneratorOptions::new()
+            .with_seed(1)
+            .with_num_given(4)
+            .with_level(Level::Junior);
+        let puzzle = generate(&options).unwrap();
+        assert_eq!(puzzle.rating.level(), Level::Junior);
+        assert_eq!(rate(&puzzle.pieces), Ok(Some(puzzle.rating)));
+    }
+}
@@ -0,0 +1,155 @@
+//! Hints for players who are stuck: the next piece to place, rather than the whole solution.
+
+use crate::board::{placement_mask, placements, BinaryBoard, NUM_CELLS};
+use crate::pieces::*;
+use crate::solver::{solve_with_options, ColorSet, SolveOptions, SolveOutcome, COLOR_LIST};
+
+/// What to do next on a partial board.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Hint {
+    /// The only piece that can cover some cell, or the only place left for a piece.
+    Forced(PlacedPiece),
+    /// No placement is forced: a piece of a solution, covering the first empty cell.
+    FromSolution(PlacedPiece),
+    /// The board is full.
+    Solved,
+    /// The board can't be completed.
+    DeadEnd,
+}
+
+/// The cells covered by the pieces, and the colors left.
+pub(crate) fn board_state(pieces: &[PlacedPiece]) -> (u64, ColorSet) {
+    let mut filled = 0u64;
+    let mut colors_left = ColorSet::full();
+    for p in pieces {
+        filled |= placement_mask(*p).unwrap_or(0);
+        colors_left.remove(p.piece.color());
+    }
+    (filled, colors_left)
+}
+
+/// A placement that is the only one covering an empty cell, or the only one left for a piece.
+/// The faces are ignored.
+///
+/// If the board can be completed, every solution has this placement.
+pub(crate) fn forced_placement(filled: u64, colors_left: ColorSet) -> Option<PlacedPiece> {
+    // The number of placements covering each cell, and one of them.
+    let mut cell_count = [0u32; NUM_CELLS as usize];
+    let mut cell_placement = [None; NUM_CELLS as usize];
+    let mut color_count = [0u32; 10];
+    let mut color_placement = [None; 10];
+    for top_left in 0..NUM_CELLS {
+        for &c in COLOR_LIST.iter() {
+            if !colors_left.contains(c) {
+                continue;
+            }
+            for placement in placements(top_left, c) {
+                if placement.mask & filled != 0 {
+                    continue;
+                }
+                let piece = Some(PlacedPiece {
+                    piece: placement.piece(),
+                    top_left,
+                });
+                color_count[c as usize] += 1;
+                color_placement[c as usize] = piece;
+                for (cell, count) in cell_count.iter_mut().enumerate() {
+                    if placement.mask & (1 << cell) != 0 {
+                        *count += 1;
+                        cell_placement[cell] = piece;
+                    }
+                }
+            }
+        }
+    }
+    (0..NUM_CELLS as usize)
+        .find(|&cell| filled & (1 << cell) == 0 && cell_count[cell] == 1)
+        .and_then(|cell| cell_placement[cell])
+        .or_else(|| {
+            COLOR_LIST
+                .iter()
+                .find(|&&c| colors_left.contains(c) && color_count[c as usize] == 1)
+                .and_then(|&c| color_placement[c as usize])
+        })
+}
+
+/// The piece to place next on the partial board made of `pieces`.
+///
+/// A forced placement is preferred, so that the player can understand why. Otherwise, the hint
+/// is the piece of the solution covering the first empty cell. If the puzzle has several
+/// solutions, the one found first by the solver is used.
+pub fn hint(pieces: &[PlacedPiece]) -> Hint {
+    let solution = match solve_with_options::<BinaryBoard>(pieces, &SolveOptions::new()) {
+        SolveOutcome::Solved(solution) => solution,
+        _ => return Hint::DeadEnd,
+    };
+    let (filled, colors_left) = board_state(pieces);
+    if colors_left.is_empty() {
+        return Hint::Solved;
+    }
+    if let Some(piece) = forced_placement(filled, colors_left) {
+        return Hint::Forced(piece);
+    }
+    let first_empty = (!filled).trailing_zeros();
+    solution[pieces.len()..]
+        .iter()
+        .find(|p| placement_mask(**p).unwrap() & (1 << first_empty) != 0)
+        .map_or(Hint::DeadEnd, |p| Hint::FromSolution(*p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BinaryBoard, Board};
+    use crate::puzzles::*;
+    use crate::solver::solve;
+
+    #[test]
+    fn hint_from_solution() {
+        let solution = solve::<BinaryBoard>(&*PIECES_117).unwrap();
+        let hint = hint(&*PIECES_117);
+        assert_eq!(hint, Hint::FromSolution(solution[2]));
+        // Following the hints solves the puzzle.
+        let mut pieces = PIECES_117.to_vec();
+        loop {
+            match super::hint(&pieces) {
+                Hint::Forced(p) | Hint::FromSolution(p) => pieces.push(p),
+                Hint::Solved => break,
+                Hint::DeadEnd => panic!("Dead end with {:?}", pieces),
+            }
+        }
+        assert_eq!(pieces.len(), 10);
+        assert_eq!(
+            BinaryBoard::from_placed_piece_list(&pieces),
+            BinaryBoard::from_placed_piece_list(&solution)
+        );
+    }
+
+    #[test]
+    fn hint_forced() {
+        let solution = solve::<BinaryBoard>(&*PIECES_49).unwrap();
+        // Without the last piece, it can only go in the hole left.
+        assert_eq!(hint(&solution[..9]), Hint::Forced(solution[9]));
+        assert_eq!(hint(&solution), Hint::Solved);
+    }
+
+    #[test]
+    fn hint_dead_end() {
+        let piece = |color, top_left| PlacedPiece {
+            piece: Piece::new()
+                .with_color(color)
+                .with_face(Face::A)
+                .with_orientation(Orientation::Up),
+            top_left,
+        };
+        assert_eq!(
+            hint(&[piece(Color::Yellow, 0), piece(Color::Blue, 2)]),
+            Hint::DeadEnd
+        );
+        // Overlapping pieces.
+        assert_eq!(
+            hint(&[piece(Color::Yellow, 0), piece(Color::Blue, 0)]),
+            Hint::DeadEnd
+        );
+    }
+}
@@ -1,7 +1,13 @@
 #![forbid(unsafe_code)]
 pub mod board;
+pub mod database;
+pub mod generator;
+pub mod hint;
 pub mod pieces;
 pub mod puzzles;
+pub mod rating;
 pub mod solver;
+pub mod symmetry;
+pub mod validation;
 
-pub use solver::solve;
+pub use solver::{count_solutions, is_unique, solve, solve_all, solve_with_options, try_solve};
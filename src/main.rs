@@ -1,18 +1,761 @@
 #![forbid(unsafe_code)]
-mod board;
-mod pieces;
-mod puzzles;
-mod solver;
-use board::*;
+use iqfit_solver::board::*;
+use iqfit_solver::database::SolutionDatabase;
+use iqfit_solver::pieces::{Face, Orientation, PieceSet, PlacedPiece};
+use iqfit_solver::puzzles::{self, format, Level};
+use iqfit_solver::rating::{self, Rating};
+use iqfit_solver::solver::{self, DlxSolver, GeometrySolver, PieceSetSolver, Solver};
+use iqfit_solver::symmetry;
+use serde::Serialize;
+use std::time::{Duration, Instant};
 
-fn main() {
-    let board = DisplayBoard::from_placed_piece_list(&*puzzles::PIECES_117).unwrap();
-    println!("{}", board);
-    let solution = solver::solve::<DisplayBoard>(&*puzzles::PIECES_117);
-    if let Some(pieces) = solution {
-        println!("Solving successful!");
-        println!("{}", DisplayBoard::from_placed_piece_list(&pieces).unwrap());
+const USAGE: &str = "Usage: iqfit [OPTIONS] COMMAND
+
+Commands:
+  solve PUZZLE          Print a solution of the puzzle
+  count PUZZLE          Count the solutions of the puzzle
+  check-unique PUZZLE   Check that the puzzle has exactly one solution
+  rate PUZZLE           Rate the difficulty of the puzzle
+  render PUZZLE         Print the puzzle
+  validate PUZZLE       Check that the puzzle file is well-formed and can be solved
+  list-puzzles          List the booklet puzzles transcribed so far
+  build-database FILE   Find every solution of the empty board and save them to FILE
+
+PUZZLE is the number of a booklet puzzle, a puzzle file, or - for stdin.
+
+Options:
+  --backend BACKEND     binary (default), display, dlx or parallel
+  --format FORMAT       color (default), ascii or json
+  --limit N             Stop counting at N solutions
+  --distinct            Count the solutions up to rotation and mirroring of the board
+  --database FILE       Look the solutions up in a database instead of searching
+  --pieces FILE         Use the pieces of a shape file instead of the standard ones
+  --stats               Print timing and search statistics to stderr
+  -h, --help            Print this help
+
+With --pieces, PUZZLE is a file listing the given pieces by name, any piece can be on any face,
+and only solve, count, check-unique, render and validate can be used.
+
+Exit codes:
+  0  Success: solved, unique, valid
+  1  No solution, not unique, invalid puzzle
+  2  Invalid command line
+  3  A puzzle or database file couldn't be read or written";
+
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_INPUT: i32 = 3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Backend {
+    Binary,
+    Display,
+    Dlx,
+    Parallel,
+}
+
+impl Backend {
+    fn solve(self, pieces: &[PlacedPiece]) -> Option<Vec<PlacedPiece>> {
+        match self {
+            Backend::Binary => solver::solve::<BinaryBoard>(pieces),
+            Backend::Display => solver::solve::<DisplayBoard>(pieces),
+            Backend::Dlx => DlxSolver.solve(pieces),
+            Backend::Parallel => solver::solve_parallel::<BinaryBoard>(
+                pieces,
+                solver::parallel::default_num_threads(),
+            ),
+        }
+    }
+
+    fn count_solutions(self, pieces: &[PlacedPiece], limit: Option<u64>) -> u64 {
+        match self {
+            Backend::Binary => solver::count_solutions::<BinaryBoard>(pieces, limit),
+            Backend::Display => solver::count_solutions::<DisplayBoard>(pieces, limit),
+            Backend::Dlx => DlxSolver.count_solutions(pieces, limit),
+            Backend::Parallel => solver::count_solutions_parallel::<BinaryBoard>(
+                pieces,
+                limit,
+                solver::parallel::default_num_threads(),
+            ),
+        }
+    }
+
+    /// The solver of the backend for the pieces of a shape file.
+    fn piece_set_solver(self, set: &PieceSet) -> Result<Box<dyn Solver>, Error> {
+        match self {
+            Backend::Binary => Ok(Box::new(PieceSetSolver::<BinaryBoard>::new(set))),
+            Backend::Display => Ok(Box::new(PieceSetSolver::<DisplayBoard>::new(set))),
+            Backend::Dlx => Ok(Box::new(
+                GeometrySolver::new(BoardGeometry::STANDARD).with_pieces(set.clone()),
+            )),
+            Backend::Parallel => Err(Error::usage(
+                "The parallel backend can't be used with --pieces".to_owned(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum OutputFormat {
+    Color,
+    Ascii,
+    Json,
+}
+
+#[derive(Debug)]
+struct Args {
+    command: String,
+    puzzle: Option<String>,
+    backend: Backend,
+    format: OutputFormat,
+    limit: Option<u64>,
+    distinct: bool,
+    database: Option<String>,
+    pieces: Option<String>,
+    stats: bool,
+}
+
+/// An error that stops the command, with its exit code.
+struct Error {
+    code: i32,
+    message: String,
+}
+
+impl Error {
+    fn usage(message: String) -> Self {
+        Error {
+            code: EXIT_USAGE,
+            message,
+        }
+    }
+
+    fn input(message: String) -> Self {
+        Error {
+            code: EXIT_INPUT,
+            message,
+        }
+    }
+
+    fn invalid(message: String) -> Self {
+        Error {
+            code: EXIT_FAILURE,
+            message,
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Args, Error> {
+    let mut positional = Vec::new();
+    let mut backend = Backend::Binary;
+    let mut format = OutputFormat::Color;
+    let mut limit = None;
+    let mut distinct = false;
+    let mut database = None;
+    let mut pieces = None;
+    let mut stats = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| Error::usage(format!("Missing value for {}", name)))
+        };
+        match arg.as_str() {
+            "--backend" => {
+                backend = match value("--backend")?.as_str() {
+                    "binary" => Backend::Binary,
+                    "display" => Backend::Display,
+                    "dlx" => Backend::Dlx,
+                    "parallel" => Backend::Parallel,
+                    b => return Err(Error::usage(format!("Unknown backend: {}", b))),
+                }
+            }
+            "--format" => {
+                format = match value("--format")?.as_str() {
+                    "color" => OutputFormat::Color,
+                    "ascii" => OutputFormat::Ascii,
+                    "json" => OutputFormat::Json,
+                    f => return Err(Error::usage(format!("Unknown format: {}", f))),
+                }
+            }
+            "--limit" => {
+                let l = value("--limit")?;
+                limit = Some(
+                    l.parse()
+                        .map_err(|_| Error::usage(format!("Invalid limit: {}", l)))?,
+                );
+            }
+            "--distinct" => distinct = true,
+            "--database" => database = Some(value("--database")?.clone()),
+            "--pieces" => pieces = Some(value("--pieces")?.clone()),
+            "--stats" => stats = true,
+            "-h" | "--help" => {
+                return Ok(Args {
+                    command: "help".to_owned(),
+                    puzzle: None,
+                    backend,
+                    format,
+                    limit,
+                    distinct,
+                    database,
+                    pieces,
+                    stats,
+                })
+            }
+            "-" => positional.push(arg.clone()),
+            a if a.starts_with('-') => return Err(Error::usage(format!("Unknown option: {}", a))),
+            _ => positional.push(arg.clone()),
+        }
+    }
+    let mut positional = positional.into_iter();
+    let command = positional
+        .next()
+        .ok_or_else(|| Error::usage("Missing command".to_owned()))?;
+    let puzzle = positional.next();
+    if let Some(extra) = positional.next() {
+        return Err(Error::usage(format!("Unexpected argument: {}", extra)));
+    }
+    if pieces.is_some() && (distinct || database.is_some()) {
+        return Err(Error::usage(
+            "--pieces can't be used with --distinct or --database".to_owned(),
+        ));
+    }
+    // Only the backtracking solver enumerates the solutions to compare them.
+    if distinct && database.is_some() {
+        return Err(Error::usage(
+            "--distinct can't be used with --database".to_owned(),
+        ));
+    }
+    if distinct && matches!(backend, Backend::Dlx | Backend::Parallel) {
+        return Err(Error::usage(
+            "--distinct can only be used with the binary or display backend".to_owned(),
+        ));
+    }
+    Ok(Args {
+        command,
+        puzzle,
+        backend,
+        format,
+        limit,
+        distinct,
+        database,
+        pieces,
+        stats,
+    })
+}
+
+/// Load the puzzle from a built-in puzzle number, a file, or stdin. With the pieces of a shape
+/// file, only files in the list format can be used.
+fn load_puzzle(puzzle: &str, set: Option<&PieceSet>) -> Result<Vec<PlacedPiece>, Error> {
+    if let Some(set) = set {
+        if puzzle.parse::<u32>().is_ok() {
+            return Err(Error::usage(
+                "Booklet puzzles can't be used with --pieces".to_owned(),
+            ));
+        }
+        let text = read_puzzle_text(puzzle)?;
+        return format::parse_list_with(&text, set)
+            .map_err(|e| Error::input(format!("{}:{}", puzzle, e)));
+    }
+    if let Ok(number) = puzzle.parse::<u32>() {
+        return puzzles::puzzle(number)
+            .map(<[PlacedPiece]>::to_vec)
+            .ok_or_else(|| {
+                if (1..=puzzles::NUM_PUZZLES).contains(&number) {
+                    Error::input(format!("Puzzle {} hasn't been transcribed yet", number))
+                } else {
+                    Error::input(format!("No puzzle {} in the booklet", number))
+                }
+            });
+    }
+    let text = read_puzzle_text(puzzle)?;
+    format::parse_puzzle(&text).map_err(|e| Error::input(format!("{}:{}", puzzle, e)))
+}
+
+/// Load the puzzle like `load_puzzle`, and check that it can be solved: the file format already
+/// rules out overlaps, but not too many pieces on a face. The faces of the pieces of a shape
+/// file are unconstrained.
+fn load_valid_puzzle(puzzle: &str, set: Option<&PieceSet>) -> Result<Vec<PlacedPiece>, Error> {
+    let pieces = load_puzzle(puzzle, set)?;
+    if set.is_some() {
+        return Ok(pieces);
+    }
+    solver::check_pieces::<BinaryBoard>(&pieces)
+        .map_err(|e| Error::invalid(format!("{}: invalid puzzle: {}", puzzle, e)))?;
+    Ok(pieces)
+}
+
+fn read_puzzle_text(puzzle: &str) -> Result<String, Error> {
+    let result = if puzzle == "-" {
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut text).map(|_| text)
     } else {
-        println!("Solving failed...");
+        std::fs::read_to_string(puzzle)
+    };
+    result.map_err(|e| Error::input(format!("Could not read {}: {}", puzzle, e)))
+}
+
+/// Load the solution database given with `--database`, if any.
+fn load_database(args: &Args) -> Result<Option<SolutionDatabase>, Error> {
+    let path = match &args.database {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let file = std::fs::File::open(path)
+        .map_err(|e| Error::input(format!("Could not read {}: {}", path, e)))?;
+    SolutionDatabase::read(std::io::BufReader::new(file))
+        .map(Some)
+        .map_err(|e| Error::input(format!("Could not read {}: {}", path, e)))
+}
+
+/// Load the shape file given with `--pieces`, if any.
+fn load_piece_set(args: &Args) -> Result<Option<PieceSet>, Error> {
+    let path = match &args.pieces {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| Error::input(format!("Could not read {}: {}", path, e)))?;
+    PieceSet::parse(&text)
+        .map(Some)
+        .map_err(|e| Error::input(format!("{}:{}", path, e)))
+}
+
+/// A solution, or the given pieces, in JSON.
+#[derive(Serialize)]
+struct BoardOutput<'a> {
+    pieces: Option<&'a [PlacedPiece]>,
+    /// The rows of the board, like a serialized `DisplayBoard`.
+    board: Option<DisplayBoard>,
+}
+
+impl<'a> BoardOutput<'a> {
+    fn new(pieces: &'a [PlacedPiece]) -> Self {
+        BoardOutput {
+            pieces: Some(pieces),
+            board: DisplayBoard::from_placed_piece_list(pieces),
+        }
+    }
+}
+
+/// The pieces of a shape file in JSON.
+#[derive(Serialize)]
+struct SetPiecesOutput<'a> {
+    pieces: Option<Vec<NamedPiece<'a>>>,
+}
+
+/// Like a serialized `PlacedPiece`, with the name of the piece in its shape file instead of its
+/// color.
+#[derive(Serialize)]
+struct NamedPiece<'a> {
+    piece: NamedPieceFields<'a>,
+    top_left: u8,
+}
+
+#[derive(Serialize)]
+struct NamedPieceFields<'a> {
+    name: &'a str,
+    face: Face,
+    orientation: Orientation,
+}
+
+impl<'a> NamedPiece<'a> {
+    fn new(piece: PlacedPiece, set: &'a PieceSet) -> Self {
+        NamedPiece {
+            piece: NamedPieceFields {
+                name: set.name(piece.piece.color() as u8),
+                face: piece.piece.face(),
+                orientation: piece.piece.orientation(),
+            },
+            top_left: piece.top_left,
+        }
     }
 }
+
+#[derive(Serialize)]
+struct CountOutput {
+    count: u64,
+    limit_reached: bool,
+}
+
+#[derive(Serialize)]
+struct UniqueOutput {
+    unique: bool,
+    solutions: u64,
+}
+
+#[derive(Serialize)]
+struct RatingOutput<'a> {
+    #[serde(flatten)]
+    rating: &'a Rating,
+    level: Level,
+}
+
+#[derive(Serialize)]
+struct ValidateOutput {
+    valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ValidateError>,
+}
+
+/// Why a puzzle is invalid, with the position of the error in the file if it couldn't be parsed.
+#[derive(Serialize)]
+struct ValidateError {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<usize>,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct PuzzleOutput {
+    number: u32,
+    level: Level,
+    pieces: &'static [PlacedPiece],
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string(value).unwrap());
+}
+
+fn print_board(pieces: &[PlacedPiece], format: OutputFormat) {
+    match format {
+        OutputFormat::Color => {
+            print!("{}", DisplayBoard::from_placed_piece_list(pieces).unwrap())
+        }
+        OutputFormat::Ascii => print!("{}", format::write_grid(pieces).unwrap()),
+        OutputFormat::Json => print_json(&BoardOutput::new(pieces)),
+    }
+}
+
+/// Print pieces from a shape file: in the list format with their names, after the board with a
+/// color per piece. In JSON, the pieces have a name instead of a color.
+fn print_set_pieces(pieces: &[PlacedPiece], format: OutputFormat, set: &PieceSet) {
+    match format {
+        OutputFormat::Color => {
+            let table = PlacementTable::new(set);
+            let board = pieces.iter().try_fold(DisplayBoard::empty(), |board, p| {
+                board.maybe_with_placement(&table.placement(*p)?, p.top_left)
+            });
+            print!("{}", board.unwrap());
+            print!("{}", format::write_list_with(pieces, set));
+        }
+        OutputFormat::Ascii => print!("{}", format::write_list_with(pieces, set)),
+        OutputFormat::Json => print_json(&SetPiecesOutput {
+            pieces: Some(pieces.iter().map(|&p| NamedPiece::new(p, set)).collect()),
+        }),
+    }
+}
+
+fn print_elapsed(stats: bool, elapsed: Duration) {
+    if stats {
+        eprintln!("Elapsed: {:?}", elapsed);
+    }
+}
+
+fn puzzle_arg(args: &Args) -> Result<&str, Error> {
+    args.puzzle
+        .as_deref()
+        .ok_or_else(|| Error::usage(format!("Missing puzzle for {}", args.command)))
+}
+
+fn solve(args: &Args) -> Result<i32, Error> {
+    let set = load_piece_set(args)?;
+    let pieces = load_valid_puzzle(puzzle_arg(args)?, set.as_ref())?;
+    let database = load_database(args)?;
+    let start = Instant::now();
+    let solution = if let Some(set) = &set {
+        let solution = args.backend.piece_set_solver(set)?.solve(&pieces);
+        print_elapsed(args.stats, start.elapsed());
+        solution
+    } else if let Some(database) = &database {
+        let solution = database.solve(&pieces);
+        print_elapsed(args.stats, start.elapsed());
+        solution
+    } else if args.stats && args.backend == Backend::Binary {
+        let (solution, stats) = solver::solve_with_stats::<BinaryBoard>(&pieces);
+        eprint!("{}", stats);
+        solution
+    } else {
+        let solution = args.backend.solve(&pieces);
+        print_elapsed(args.stats, start.elapsed());
+        solution
+    };
+    match solution {
+        Some(solution) => {
+            match &set {
+                Some(set) => print_set_pieces(&solution, args.format, set),
+                None => print_board(&solution, args.format),
+            }
+            Ok(0)
+        }
+        None => {
+            match args.format {
+                OutputFormat::Json if set.is_some() => {
+                    print_json(&SetPiecesOutput { pieces: None })
+                }
+                OutputFormat::Json => print_json(&BoardOutput {
+                    pieces: None,
+                    board: None,
+                }),
+                _ => println!("No solution"),
+            }
+            Ok(EXIT_FAILURE)
+        }
+    }
+}
+
+fn count(args: &Args) -> Result<i32, Error> {
+    let set = load_piece_set(args)?;
+    let pieces = load_valid_puzzle(puzzle_arg(args)?, set.as_ref())?;
+    let database = load_database(args)?;
+    let start = Instant::now();
+    let count = if let Some(set) = &set {
+        args.backend
+            .piece_set_solver(set)?
+            .count_solutions(&pieces, args.limit)
+    } else if args.distinct {
+        match args.backend {
+            Backend::Display => {
+                symmetry::count_solution_classes::<DisplayBoard>(&pieces, args.limit)
+            }
+            _ => symmetry::count_solution_classes::<BinaryBoard>(&pieces, args.limit),
+        }
+    } else if let Some(database) = &database {
+        database.count_solutions(&pieces, args.limit)
+    } else {
+        args.backend.count_solutions(&pieces, args.limit)
+    };
+    print_elapsed(args.stats, start.elapsed());
+    let limit_reached = args.limit == Some(count);
+    match args.format {
+        OutputFormat::Json => print_json(&CountOutput {
+            count,
+            limit_reached,
+        }),
+        _ if limit_reached => println!("Solutions: at least {}", count),
+        _ => println!("Solutions: {}", count),
+    }
+    Ok(if count == 0 { EXIT_FAILURE } else { 0 })
+}
+
+fn check_unique(args: &Args) -> Result<i32, Error> {
+    let set = load_piece_set(args)?;
+    let pieces = load_valid_puzzle(puzzle_arg(args)?, set.as_ref())?;
+    let database = load_database(args)?;
+    let start = Instant::now();
+    let count = match (&set, &database) {
+        (Some(set), _) => args
+            .backend
+            .piece_set_solver(set)?
+            .count_solutions(&pieces, Some(2)),
+        (None, Some(database)) => database.count_solutions(&pieces, Some(2)),
+        (None, None) => args.backend.count_solutions(&pieces, Some(2)),
+    };
+    print_elapsed(args.stats, start.elapsed());
+    let unique = count == 1;
+    match args.format {
+        OutputFormat::Json => print_json(&UniqueOutput {
+            unique,
+            solutions: count,
+        }),
+        _ if unique => println!("Unique"),
+        _ if count == 0 => println!("Not unique: no solution"),
+        _ => println!("Not unique: several solutions"),
+    }
+    Ok(if unique { 0 } else { EXIT_FAILURE })
+}
+
+fn rate(args: &Args) -> Result<i32, Error> {
+    let pieces = load_valid_puzzle(puzzle_arg(args)?, None)?;
+    let start = Instant::now();
+    let rating = rating::rate(&pieces).map_err(|e| Error::invalid(e.to_string()))?;
+    print_elapsed(args.stats, start.elapsed());
+    let rating = match rating {
+        Some(rating) => rating,
+        None => {
+            match args.format {
+                OutputFormat::Json => println!("null"),
+                _ => println!("No solution"),
+            }
+            return Ok(EXIT_FAILURE);
+        }
+    };
+    match args.format {
+        OutputFormat::Json => print_json(&RatingOutput {
+            rating: &rating,
+            level: rating.level(),
+        }),
+        _ => {
+            println!("Level: {:?}", rating.level());
+            println!("Score: {:.1}", rating.score);
+            println!("Given pieces: {}", rating.given_pieces);
+            println!("Nodes: {}", rating.nodes);
+            println!("Branching factor: {:.2}", rating.branching_factor);
+            println!("Forced moves: {}", rating.forced_moves);
+            println!("Relaxed solutions: {}", rating.relaxed_solutions);
+        }
+    }
+    Ok(0)
+}
+
+fn render(args: &Args) -> Result<i32, Error> {
+    let set = load_piece_set(args)?;
+    let pieces = load_puzzle(puzzle_arg(args)?, set.as_ref())?;
+    match &set {
+        Some(set) => print_set_pieces(&pieces, args.format, set),
+        None => print_board(&pieces, args.format),
+    }
+    Ok(0)
+}
+
+fn validate(args: &Args) -> Result<i32, Error> {
+    let set = load_piece_set(args)?;
+    let puzzle = puzzle_arg(args)?;
+    if puzzle.parse::<u32>().is_ok() {
+        // Built-in puzzles are always valid, if they exist.
+        load_puzzle(puzzle, set.as_ref())?;
+        print_validity(puzzle, Ok(()), args.format);
+        return Ok(0);
+    }
+    let text = read_puzzle_text(puzzle)?;
+    let parsed = match &set {
+        Some(set) => format::parse_list_with(&text, set),
+        None => format::parse_puzzle(&text),
+    };
+    let pieces = match parsed {
+        Ok(pieces) => pieces,
+        Err(e) => {
+            match args.format {
+                OutputFormat::Json => print_json(&ValidateOutput {
+                    valid: false,
+                    error: Some(ValidateError {
+                        line: Some(e.line),
+                        column: Some(e.column),
+                        message: e.kind.to_string(),
+                    }),
+                }),
+                _ => println!("{}:{}", puzzle, e),
+            }
+            return Ok(EXIT_FAILURE);
+        }
+    };
+    // The pieces are well-formed, but can still break the rules of a solution as a whole. The
+    // faces of the pieces of a shape file are unconstrained.
+    let result = match set {
+        Some(_) => Ok(()),
+        None => solver::check_pieces::<BinaryBoard>(&pieces),
+    };
+    let valid = result.is_ok();
+    print_validity(puzzle, result.map_err(|e| e.to_string()), args.format);
+    Ok(if valid { 0 } else { EXIT_FAILURE })
+}
+
+/// Print whether the puzzle is valid, or why it isn't.
+fn print_validity(puzzle: &str, result: Result<(), String>, format: OutputFormat) {
+    match (result, format) {
+        (Ok(_), OutputFormat::Json) => print_json(&ValidateOutput {
+            valid: true,
+            error: None,
+        }),
+        (Ok(_), _) => println!("Valid"),
+        (Err(message), OutputFormat::Json) => print_json(&ValidateOutput {
+            valid: false,
+            error: Some(ValidateError {
+                line: None,
+                column: None,
+                message,
+            }),
+        }),
+        (Err(message), _) => println!("{}: {}", puzzle, message),
+    }
+}
+
+fn list_puzzles(args: &Args) -> Result<i32, Error> {
+    match args.format {
+        OutputFormat::Json => {
+            let puzzles: Vec<PuzzleOutput> = puzzles::catalog()
+                .map(|p| PuzzleOutput {
+                    number: p.number,
+                    level: p.level,
+                    pieces: p.pieces,
+                })
+                .collect();
+            print_json(&puzzles);
+        }
+        _ => {
+            for p in puzzles::catalog() {
+                println!("{:>3}: {:?}, {} pieces", p.number, p.level, p.pieces.len());
+            }
+            println!(
+                "{} of the {} booklet puzzles have been transcribed",
+                puzzles::TRANSCRIBED_PUZZLES.len(),
+                puzzles::NUM_PUZZLES
+            );
+        }
+    }
+    Ok(0)
+}
+
+fn build_database(args: &Args) -> Result<i32, Error> {
+    let path = args
+        .puzzle
+        .as_deref()
+        .ok_or_else(|| Error::usage("Missing file for build-database".to_owned()))?;
+    let start = Instant::now();
+    let database = SolutionDatabase::build(solver::parallel::default_num_threads());
+    print_elapsed(args.stats, start.elapsed());
+    let written =
+        std::fs::File::create(path).and_then(|file| database.write(std::io::BufWriter::new(file)));
+    written.map_err(|e| Error::input(format!("Could not write {}: {}", path, e)))?;
+    println!("Solutions: {}", database.len());
+    Ok(0)
+}
+
+fn run(args: &[String]) -> Result<i32, Error> {
+    let args = parse_args(args)?;
+    let takes_pieces = [
+        "solve",
+        "count",
+        "check-unique",
+        "render",
+        "validate",
+        "help",
+    ];
+    if args.pieces.is_some() && !takes_pieces.contains(&args.command.as_str()) {
+        return Err(Error::usage(format!(
+            "{} can't be used with --pieces",
+            args.command
+        )));
+    }
+    match args.command.as_str() {
+        "solve" => solve(&args),
+        "count" => count(&args),
+        "check-unique" => check_unique(&args),
+        "rate" => rate(&args),
+        "render" => render(&args),
+        "validate" => validate(&args),
+        "list-puzzles" => list_puzzles(&args),
+        "build-database" => build_database(&args),
+        "help" => {
+            println!("{}", USAGE);
+            Ok(0)
+        }
+        command => Err(Error::usage(format!("Unknown command: {}", command))),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let code = match run(&args) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e.message);
+            if e.code == EXIT_USAGE {
+                eprintln!("{}", USAGE);
+            }
+            e.code
+        }
+    };
+    std::process::exit(code);
+}
@@ -1,7 +1,17 @@
+// The bitfield macro expands the field types with parentheses.
+#![allow(unused_parens)]
 use modular_bitfield::{bitfield, BitfieldSpecifier};
 
+mod piece_set;
+pub use piece_set::{Ball, PieceSet, ShapeError, ShapeErrorKind, MAX_OFFSET, MAX_PIECES};
+
 /// Which physical piece.
 #[derive(BitfieldSpecifier, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
 #[bits = 4]
 pub enum Color {
     Yellow,
@@ -18,6 +28,7 @@ pub enum Color {
 
 /// Which face:
 #[derive(BitfieldSpecifier, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Face {
     /// One ball sticking out.
     A,
@@ -29,6 +40,11 @@ pub enum Face {
 /// Up has the main line vertical, and the extra ball(s) to the right.
 /// The rest are successive 90 degrees rotations to the right.
 #[derive(BitfieldSpecifier, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Orientation {
     Up,
     Right,
@@ -39,28 +55,96 @@ pub enum Orientation {
 #[bitfield(filled = false)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Piece {
-    #[skip(getters)]
     pub orientation: Orientation,
     pub face: Face,
     pub color: Color,
 }
 
+impl Default for Piece {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Piece {
     pub const fn as_byte(&self) -> u8 {
         self.into_bytes()[0]
     }
+
+    /// The inverse of `as_byte`. Panics if the byte doesn't represent a piece.
+    #[inline]
+    pub fn from_byte(byte: u8) -> Self {
+        Self::from_bytes([byte]).expect("Invalid piece byte")
+    }
+}
+
+/// How a `Piece` is serialized: its fields, rather than its byte.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PieceFields {
+    color: Color,
+    face: Face,
+    orientation: Orientation,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Piece {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PieceFields {
+            color: self.color(),
+            face: self.face(),
+            orientation: self.orientation(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Piece {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = PieceFields::deserialize(deserializer)?;
+        Ok(Piece::new()
+            .with_color(fields.color)
+            .with_face(fields.face)
+            .with_orientation(fields.orientation))
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlacedPiece {
     /// Which piece, in which orientation.
     pub piece: Piece,
-    /// The position of the top-left corner, from 0 to 50.
+    /// The position of the top-left corner, from 0 to `NUM_CELLS - 1`, row by row.
     pub top_left: u8,
 }
 
 impl PlacedPiece {
     pub const fn top_left_coords(&self) -> (u8, u8) {
-        (self.top_left / 10, self.top_left % 10)
+        use crate::board::WIDTH;
+        (self.top_left / WIDTH, self.top_left % WIDTH)
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_placed_piece() {
+        let piece = PlacedPiece {
+            piece: Piece::new()
+                .with_color(Color::LightBlue)
+                .with_face(Face::B)
+                .with_orientation(Orientation::Right),
+            top_left: 3,
+        };
+        let json = serde_json::to_string(&piece).unwrap();
+        assert_eq!(
+            json,
+            r#"{"piece":{"color":"light-blue","face":"B","orientation":"right"},"top_left":3}"#
+        );
+        assert_eq!(serde_json::from_str::<PlacedPiece>(&json).unwrap(), piece);
+        assert!(serde_json::from_str::<Color>(r#""LightBlue""#).is_err());
     }
 }
@@ -0,0 +1,535 @@
+//! Piece shapes loaded at runtime, e.g. for replacement or home-made pieces.
+//!
+//! The shapes of the standard pieces are compiled in the board tables by the build script. A
+//! `PieceSet` describes the pieces instead, for the solvers that take one:
+//! `solver::PieceSetSolver` on the standard board, and `solver::GeometrySolver` on boards of any
+//! size.
+//!
+//! The pieces of a set have names of their own, and are numbered from 0 in the order they were
+//! added, up to `MAX_PIECES`. A `Piece` of the set keeps this number in place of its color:
+//! piece `i` has the `i`-th color of `Color`, see `PieceSet::piece`. The standard set names its
+//! pieces after their colors, in the order of `Color`, so its pieces are the standard ones.
+//!
+//! A shape file has a piece per line: its name, the length of its base line, and the extra
+//! balls of each face, as `row,column` from the top of the base line in the `Up` orientation.
+//! The base line goes down from `0,0`, and the extra balls are usually on its right. The
+//! coordinates are at most `MAX_OFFSET` away from `0,0`. Lines starting with `#` are comments,
+//! and empty lines are ignored. These are the standard pieces:
+//!
+//! ```text
+//! yellow      3 A 0,1 3,0 B 0,1 1,1 3,0
+//! orange      3 A 3,0 2,1 B 3,0 1,1 3,1
+//! red         3 A 3,0 3,1 B 3,0 0,1 3,1
+//! pink        3 A 3,0 1,1 B 3,0 2,1 3,1
+//! light-green 3 A 2,1     B 0,1 2,1
+//! green       3 A 1,1     B 1,1 2,1
+//! light-blue  3 A 3,0 2,1 B 3,0 1,1 2,1
+//! blue        3 A 3,0 3,1 B 3,0 0,1 2,1
+//! deep-blue   3 A 1,1     B 0,1 2,1
+//! purple      3 A 0,1     B 0,1 1,1
+//! ```
+
+use crate::board::BoardGeometry;
+use crate::pieces::*;
+use crate::puzzles::format::{color_name, content_lines, fields, parse_face};
+use crate::solver::{ColorSet, COLOR_LIST};
+use std::fmt;
+
+/// A ball of a piece, as (row, column).
+pub type Ball = (i8, i8);
+
+/// The largest row or column of a ball, in absolute value. The base line of a piece has at most
+/// `MAX_OFFSET + 1` balls.
+pub const MAX_OFFSET: i8 = 15;
+
+/// The largest number of pieces in a set, as many as the colors a `Piece` can have.
+pub const MAX_PIECES: usize = 10;
+
+/// What is wrong with a shape file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShapeErrorKind {
+    /// A piece with this name was already described.
+    DuplicateName(String),
+    /// The file has more than `MAX_PIECES` pieces.
+    TooManyPieces,
+    /// The length of the base line isn't a number from 1 to `MAX_OFFSET + 1`.
+    InvalidLength(String),
+    /// A ball isn't written as `row,column`, or is more than `MAX_OFFSET` away from `0,0`.
+    InvalidBall(String),
+    /// The ball was already given for this face of the piece.
+    DuplicateBall(String),
+    /// The balls of this face of the piece are missing.
+    MissingFace(Face),
+}
+
+/// An error in a shape file, with the position where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShapeError {
+    /// The line, starting at 1.
+    pub line: usize,
+    /// The column, starting at 1.
+    pub column: usize,
+    pub kind: ShapeErrorKind,
+}
+
+impl fmt::Display for ShapeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShapeErrorKind::DuplicateName(s) => write!(f, "the {} piece is already described", s),
+            ShapeErrorKind::TooManyPieces => write!(f, "more than {} pieces", MAX_PIECES),
+            ShapeErrorKind::InvalidLength(s) => write!(f, "invalid base line length '{}'", s),
+            ShapeErrorKind::InvalidBall(s) => write!(f, "invalid ball '{}'", s),
+            ShapeErrorKind::DuplicateBall(s) => write!(f, "the ball {} is already used", s),
+            ShapeErrorKind::MissingFace(face) => write!(f, "expected face {:?}", face),
+        }
+    }
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for ShapeError {}
+
+/// The shapes of some pieces, for each face and orientation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PieceSet {
+    /// The name of each piece, by number.
+    names: Vec<String>,
+    /// The balls of each piece, indexed by `Piece::as_byte`, relative to the top-left ball and
+    /// sorted row by row. Empty for the numbers without a piece.
+    shapes: Vec<Vec<Ball>>,
+}
+
+impl PieceSet {
+    /// A set without any piece.
+    pub fn empty() -> Self {
+        PieceSet {
+            names: Vec::new(),
+            shapes: vec![Vec::new(); 8 * MAX_PIECES],
+        }
+    }
+
+    /// The standard pieces, as compiled in the board tables.
+    pub fn standard() -> Self {
+        let geometry = BoardGeometry::STANDARD;
+        let width = geometry.width() as i8;
+        let mut set = Self::empty();
+        set.names = COLOR_LIST
+            .iter()
+            .map(|&c| color_name(c).to_owned())
+            .collect();
+        for (byte, shape) in set.shapes.iter_mut().enumerate() {
+            let cells = (0..geometry.num_cells() as u8)
+                .find_map(|top_left| {
+                    geometry.piece_cells(PlacedPiece {
+                        piece: Piece::from_byte(byte as u8),
+                        top_left,
+                    })
+                })
+                .unwrap();
+            let balls: Vec<Ball> = cells
+                .iter()
+                .map(|&c| ((c as i8) / width, (c as i8) % width))
+                .collect();
+            *shape = normalized(balls);
+        }
+        set
+    }
+
+    /// Add the piece with the given name, or replace it, with the balls of each face given in
+    /// the `Up` orientation. The other orientations are its successive rotations.
+    ///
+    /// Panics if a ball is more than `MAX_OFFSET` away from `(0, 0)`, or if the set already has
+    /// `MAX_PIECES` other pieces.
+    pub fn with_piece(mut self, name: &str, face_a: &[Ball], face_b: &[Ball]) -> Self {
+        let index = match self.index_of(name) {
+            Some(index) => index,
+            None => {
+                assert!(self.len() < MAX_PIECES, "more than {} pieces", MAX_PIECES);
+                self.names.push(name.to_owned());
+                self.len() as u8 - 1
+            }
+        };
+        for (face, balls) in [(Face::A, face_a), (Face::B, face_b)].iter() {
+            assert!(
+                balls.iter().all(|&ball| is_in_range(ball)),
+                "ball out of range in {:?}",
+                balls
+            );
+            let mut balls = normalized(balls.to_vec());
+            for orientation in [
+                Orientation::Up,
+                Orientation::Right,
+                Orientation::Down,
+                Orientation::Left,
+            ]
+            .iter()
+            {
+                let piece = self
+                    .piece(index)
+                    .with_face(*face)
+                    .with_orientation(*orientation);
+                self.shapes[piece.as_byte() as usize] = balls.clone();
+                balls = normalized(balls.iter().map(|&(row, col)| (col, -row)).collect());
+            }
+        }
+        self
+    }
+
+    /// Parse a shape file, see the module documentation.
+    pub fn parse(text: &str) -> Result<Self, ShapeError> {
+        let mut set = Self::empty();
+        for (line_number, line) in content_lines(text) {
+            let error = |column, kind| ShapeError {
+                line: line_number,
+                column,
+                kind,
+            };
+            let fields = fields(line);
+            let (name_column, name) = fields[0];
+            if set.index_of(name).is_some() {
+                return Err(error(
+                    name_column,
+                    ShapeErrorKind::DuplicateName(name.to_owned()),
+                ));
+            }
+            if set.len() == MAX_PIECES {
+                return Err(error(name_column, ShapeErrorKind::TooManyPieces));
+            }
+            let end_column = line.chars().count() + 1;
+            let (column, length) = fields.get(1).copied().unwrap_or((end_column, ""));
+            let length: i8 = match length.parse() {
+                Ok(length) if length > 0 && length <= MAX_OFFSET + 1 => length,
+                _ => {
+                    return Err(error(
+                        column,
+                        ShapeErrorKind::InvalidLength(length.to_owned()),
+                    ))
+                }
+            };
+            // The fields after each face letter, up to the next one.
+            let mut faces: [Option<Vec<(usize, &str)>>; 2] = [None, None];
+            let mut current = None;
+            for &(column, field) in fields.iter().skip(2) {
+                match (parse_face(field), current) {
+                    (Some(face @ Face::A), None) | (Some(face @ Face::B), Some(Face::A)) => {
+                        faces[face as usize] = Some(Vec::new());
+                        current = Some(face);
+                    }
+                    (_, Some(face)) => {
+                        faces[face as usize].as_mut().unwrap().push((column, field));
+                    }
+                    (_, None) => {
+                        return Err(error(column, ShapeErrorKind::MissingFace(Face::A)));
+                    }
+                }
+            }
+            let base: Vec<Ball> = (0..length).map(|row| (row, 0)).collect();
+            let mut shapes = Vec::with_capacity(2);
+            for face in [Face::A, Face::B].iter() {
+                let extra = faces[*face as usize]
+                    .as_ref()
+                    .ok_or_else(|| error(end_column, ShapeErrorKind::MissingFace(*face)))?;
+                let mut balls = base.clone();
+                for &(column, field) in extra {
+                    let ball = parse_ball(field).ok_or_else(|| {
+                        error(column, ShapeErrorKind::InvalidBall(field.to_owned()))
+                    })?;
+                    if balls.contains(&ball) {
+                        return Err(error(
+                            column,
+                            ShapeErrorKind::DuplicateBall(field.to_owned()),
+                        ));
+                    }
+                    balls.push(ball);
+                }
+                shapes.push(balls);
+            }
+            set = set.with_piece(name, &shapes[0], &shapes[1]);
+        }
+        Ok(set)
+    }
+
+    /// The number of pieces in the set.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The name of the piece with this number.
+    pub fn name(&self, index: u8) -> &str {
+        &self.names[index as usize]
+    }
+
+    /// The number of the piece with this name, if it's in the set.
+    pub fn index_of(&self, name: &str) -> Option<u8> {
+        self.names.iter().position(|n| n == name).map(|i| i as u8)
+    }
+
+    /// The piece with this number, on its A face and turned up.
+    pub fn piece(&self, index: u8) -> Piece {
+        Piece::new().with_color(COLOR_LIST[index as usize])
+    }
+
+    /// The number of the piece, the opposite of `piece`.
+    pub fn index(&self, piece: Piece) -> u8 {
+        piece.color() as u8
+    }
+
+    /// The colors holding the numbers of the pieces, for the solvers that select pieces by
+    /// color.
+    pub fn colors(&self) -> ColorSet {
+        ColorSet::from_colors(&COLOR_LIST[..self.len()])
+    }
+
+    /// The balls of the piece, relative to its top-left ball and sorted row by row. Empty if the
+    /// piece isn't in the set.
+    pub fn balls(&self, piece: Piece) -> &[Ball] {
+        &self.shapes[piece.as_byte() as usize]
+    }
+
+    /// The number of balls of the piece, i.e. the number of cells it covers.
+    pub fn num_balls(&self, piece: Piece) -> usize {
+        self.balls(piece).len()
+    }
+
+    /// The cells covered by the piece on the board, or None if it doesn't fit or isn't in the
+    /// set.
+    pub fn piece_cells(&self, geometry: &BoardGeometry, piece: PlacedPiece) -> Option<Vec<u8>> {
+        let balls = self.balls(piece.piece);
+        if balls.is_empty() || piece.top_left as usize >= geometry.num_cells() {
+            return None;
+        }
+        let (width, height) = (geometry.width() as i16, geometry.height() as i16);
+        let (row, col) = (piece.top_left as i16 / width, piece.top_left as i16 % width);
+        balls
+            .iter()
+            .map(|&(ball_row, ball_col)| {
+                let (r, c) = (row + ball_row as i16, col + ball_col as i16);
+                if r < height && (0..width).contains(&c) {
+                    Some((r * width + c) as u8)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for PieceSet {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+/// The balls sorted row by row, and shifted so that the first one is at (0, 0).
+fn normalized(mut balls: Vec<Ball>) -> Vec<Ball> {
+    balls.sort_unstable();
+    if let Some(&(top, left)) = balls.first() {
+        for ball in balls.iter_mut() {
+            *ball = (ball.0 - top, ball.1 - left);
+        }
+    }
+    balls
+}
+
+fn is_in_range((row, col): Ball) -> bool {
+    let range = -MAX_OFFSET..=MAX_OFFSET;
+    range.contains(&row) && range.contains(&col)
+}
+
+/// The ball written as `row,column`, if it's in range.
+fn parse_ball(s: &str) -> Option<Ball> {
+    let mut coords = s.split(',');
+    let row = coords.next()?.parse().ok()?;
+    let col = coords.next()?.parse().ok()?;
+    match coords.next() {
+        None => Some((row, col)).filter(|&ball| is_in_range(ball)),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STANDARD_SHAPES: &str = "
+# The standard pieces.
+yellow      3 A 0,1 3,0 B 0,1 1,1 3,0
+orange      3 A 3,0 2,1 B 3,0 1,1 3,1
+red         3 A 3,0 3,1 B 3,0 0,1 3,1
+pink        3 A 3,0 1,1 B 3,0 2,1 3,1
+light-green 3 A 2,1     B 0,1 2,1
+green       3 A 1,1     B 1,1 2,1
+light-blue  3 A 3,0 2,1 B 3,0 1,1 2,1
+blue        3 A 3,0 3,1 B 3,0 0,1 2,1
+deep-blue   3 A 1,1     B 0,1 2,1
+purple      3 A 0,1     B 0,1 1,1
+";
+
+    fn error_at(line: usize, column: usize, kind: ShapeErrorKind) -> Result<PieceSet, ShapeError> {
+        Err(ShapeError { line, column, kind })
+    }
+
+    #[test]
+    fn parse_standard_shapes() {
+        let set = PieceSet::parse(STANDARD_SHAPES).unwrap();
+        assert_eq!(set, PieceSet::standard());
+        assert_eq!(set.len(), 10);
+        assert_eq!(set.colors(), ColorSet::from_colors(&COLOR_LIST));
+        for &color in COLOR_LIST.iter() {
+            let index = set.index_of(color_name(color)).unwrap();
+            assert_eq!(set.piece(index), Piece::new().with_color(color));
+        }
+        let geometry = BoardGeometry::STANDARD;
+        for byte in 0..80 {
+            for top_left in 0..geometry.num_cells() as u8 {
+                let piece = PlacedPiece {
+                    piece: Piece::from_byte(byte),
+                    top_left,
+                };
+                // The balls of the standard pieces aren't in order.
+                let expected = geometry.piece_cells(piece).map(|mut cells| {
+                    cells.sort_unstable();
+                    cells
+                });
+                assert_eq!(set.piece_cells(&geometry, piece), expected, "{:?}", piece);
+            }
+        }
+    }
+
+    #[test]
+    fn custom_pieces() {
+        // A straight line of 4 balls, with a fifth one on the B face.
+        let set = PieceSet::parse("stick 4 A B 4,0").unwrap();
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.index_of("stick"), Some(0));
+        assert_eq!(set.index_of("purple"), None);
+        assert_eq!(set.name(0), "stick");
+        assert_eq!(set.colors(), ColorSet::from_colors(&[Color::Yellow]));
+        let piece = |face, orientation| set.piece(0).with_face(face).with_orientation(orientation);
+        assert_eq!(set.index(piece(Face::B, Orientation::Down)), 0);
+        assert_eq!(set.num_balls(piece(Face::A, Orientation::Up)), 4);
+        assert_eq!(set.num_balls(piece(Face::B, Orientation::Left)), 5);
+        assert_eq!(
+            set.balls(piece(Face::A, Orientation::Right)),
+            &[(0, 0), (0, 1), (0, 2), (0, 3)]
+        );
+        let geometry = BoardGeometry::new(4, 2).unwrap();
+        let placed = |orientation, top_left| PlacedPiece {
+            piece: piece(Face::A, orientation),
+            top_left,
+        };
+        assert_eq!(
+            set.piece_cells(&geometry, placed(Orientation::Right, 4)),
+            Some(vec![4, 5, 6, 7])
+        );
+        assert_eq!(
+            set.piece_cells(&geometry, placed(Orientation::Right, 5)),
+            None
+        );
+        assert_eq!(set.piece_cells(&geometry, placed(Orientation::Up, 0)), None);
+        let missing = PlacedPiece {
+            piece: set.piece(1),
+            top_left: 0,
+        };
+        assert_eq!(set.piece_cells(&geometry, missing), None);
+        // Balls on the left of the base line move the top-left ball.
+        let set = set.with_piece("hook", &[(0, 0), (1, -1), (1, 0)], &[]);
+        let hook = set.piece(set.index_of("hook").unwrap());
+        assert_eq!(hook, Piece::new().with_color(Color::Orange));
+        assert_eq!(set.balls(hook), &[(0, 0), (1, -1), (1, 0)]);
+        assert_eq!(
+            set.piece_cells(
+                &geometry,
+                PlacedPiece {
+                    piece: hook,
+                    top_left: 0
+                }
+            ),
+            None
+        );
+        assert_eq!(
+            set.piece_cells(
+                &geometry,
+                PlacedPiece {
+                    piece: hook,
+                    top_left: 1
+                }
+            ),
+            Some(vec![1, 4, 5])
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            PieceSet::parse("red"),
+            error_at(1, 4, ShapeErrorKind::InvalidLength("".to_owned()))
+        );
+        assert_eq!(
+            PieceSet::parse("red 0 A B"),
+            error_at(1, 5, ShapeErrorKind::InvalidLength("0".to_owned()))
+        );
+        assert_eq!(
+            PieceSet::parse("red 3 1,1 A B"),
+            error_at(1, 7, ShapeErrorKind::MissingFace(Face::A))
+        );
+        assert_eq!(
+            PieceSet::parse("red 3 A 1,1"),
+            error_at(1, 12, ShapeErrorKind::MissingFace(Face::B))
+        );
+        assert_eq!(
+            PieceSet::parse("red 3 A 1,1 B 1,x"),
+            error_at(1, 15, ShapeErrorKind::InvalidBall("1,x".to_owned()))
+        );
+        assert_eq!(
+            PieceSet::parse("\nred 3 A 1,0 B"),
+            error_at(2, 9, ShapeErrorKind::DuplicateBall("1,0".to_owned()))
+        );
+        assert_eq!(
+            PieceSet::parse("stick 4 A B\nstick 3 A 0,1 B 0,1 1,1"),
+            error_at(2, 1, ShapeErrorKind::DuplicateName("stick".to_owned()))
+        );
+        let eleven: String = (0..11).map(|i| format!("p{} 1 A B\n", i)).collect();
+        assert_eq!(
+            PieceSet::parse(&eleven),
+            error_at(11, 1, ShapeErrorKind::TooManyPieces)
+        );
+        // The coordinates are bounded, so that the rotations can't overflow.
+        assert_eq!(
+            PieceSet::parse("red 3 A -128,0 B"),
+            error_at(1, 9, ShapeErrorKind::InvalidBall("-128,0".to_owned()))
+        );
+        assert_eq!(
+            PieceSet::parse("red 3 A 0,16 B"),
+            error_at(1, 9, ShapeErrorKind::InvalidBall("0,16".to_owned()))
+        );
+        assert_eq!(
+            PieceSet::parse("red 17 A B"),
+            error_at(1, 5, ShapeErrorKind::InvalidLength("17".to_owned()))
+        );
+        assert_eq!(
+            PieceSet::parse("long 16 A -15,-15 B 15,15")
+                .unwrap()
+                .num_balls(Piece::new()),
+            17
+        );
+        assert_eq!(
+            PieceSet::parse("red 3 A B 1,0").unwrap_err().to_string(),
+            "1:11: the ball 1,0 is already used"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn ball_out_of_range() {
+        PieceSet::empty().with_piece("red", &[(0, 0), (i8::MIN, 0)], &[]);
+    }
+}
@@ -1,6 +1,14 @@
+//! The puzzles of the booklet that comes with the game.
+//!
+//! The catalog is incomplete: only the puzzles listed in `TRANSCRIBED_PUZZLES` have been copied
+//! from the booklet so far. The others can't be generated, since the booklet picks its own
+//! starting layouts, and have to be transcribed by hand.
+
 use crate::pieces::*;
 use lazy_static::lazy_static;
 
+pub mod format;
+
 lazy_static! {
     pub static ref PIECES_49: [PlacedPiece; 3] = [
         PlacedPiece {
@@ -42,3 +50,140 @@ lazy_static! {
         },
     ];
 }
+
+/// The difficulty levels of the booklet, from the easiest to the hardest.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Level {
+    Starter,
+    Junior,
+    Expert,
+    Master,
+    Wizard,
+}
+
+/// The number of puzzles in the booklet, numbered from 1.
+pub const NUM_PUZZLES: u32 = 120;
+
+impl Level {
+    pub const ALL: [Level; 5] = [
+        Level::Starter,
+        Level::Junior,
+        Level::Expert,
+        Level::Master,
+        Level::Wizard,
+    ];
+}
+
+/// A puzzle of the booklet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    pub number: u32,
+    pub level: Level,
+    /// The pieces given at the start.
+    pub pieces: &'static [PlacedPiece],
+}
+
+/// The numbers of the booklet puzzles that have been transcribed, out of `NUM_PUZZLES`. The other
+/// starting layouts still have to be copied from the booklet, each with a `lazy_static` above and
+/// an entry in `puzzle` and `level`.
+pub const TRANSCRIBED_PUZZLES: [u32; 2] = [49, 117];
+
+/// The given pieces of the booklet puzzle with this number, if it has been transcribed.
+pub fn puzzle(number: u32) -> Option<&'static [PlacedPiece]> {
+    match number {
+        49 => Some(&*PIECES_49),
+        117 => Some(&*PIECES_117),
+        _ => None,
+    }
+}
+
+/// The level printed in the booklet for the puzzle with this number, if it has been transcribed.
+pub fn level(number: u32) -> Option<Level> {
+    match number {
+        49 => Some(Level::Expert),
+        117 => Some(Level::Wizard),
+        _ => None,
+    }
+}
+
+/// The transcribed puzzles of the booklet, in order. This is only part of the booklet, see
+/// `TRANSCRIBED_PUZZLES`.
+pub fn catalog() -> impl Iterator<Item = Puzzle> {
+    TRANSCRIBED_PUZZLES.iter().map(|&number| Puzzle {
+        number,
+        level: level(number).unwrap(),
+        pieces: puzzle(number).unwrap(),
+    })
+}
+
+/// The transcribed puzzles of the given level, in order.
+pub fn catalog_level(level: Level) -> impl Iterator<Item = Puzzle> {
+    catalog().filter(move |p| p.level == level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels() {
+        assert_eq!(level(49), Some(Level::Expert));
+        assert_eq!(level(117), Some(Level::Wizard));
+        assert_eq!(
+            catalog_level(Level::Wizard)
+                .map(|p| p.number)
+                .collect::<Vec<_>>(),
+            vec![117]
+        );
+    }
+
+    #[test]
+    fn transcribed_puzzles() {
+        for number in 0..=NUM_PUZZLES + 1 {
+            assert_eq!(
+                puzzle(number).is_some(),
+                TRANSCRIBED_PUZZLES.contains(&number),
+                "Puzzle {}",
+                number
+            );
+            assert_eq!(
+                level(number).is_some(),
+                TRANSCRIBED_PUZZLES.contains(&number),
+                "Puzzle {}",
+                number
+            );
+        }
+        assert_eq!(catalog().count(), TRANSCRIBED_PUZZLES.len());
+    }
+}
+
+/// Solve every puzzle of the catalog with each board.
+#[cfg(test)]
+#[generic_tests::define]
+mod catalog_tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::solver::solve;
+
+    #[test]
+    fn solve_catalog<B: Board>() {
+        for puzzle in catalog() {
+            let solution = solve::<B>(puzzle.pieces)
+                .unwrap_or_else(|| panic!("Puzzle {} has no solution", puzzle.number));
+            assert_eq!(solution.len(), 10, "Puzzle {}", puzzle.number);
+            assert_eq!(&solution[..puzzle.pieces.len()], puzzle.pieces);
+            let board = B::from_placed_piece_list(&solution).unwrap();
+            assert_eq!(board.first_empty_cell(0), None, "Puzzle {}", puzzle.number);
+        }
+    }
+
+    #[instantiate_tests(<crate::board::DisplayBoard>)]
+    mod display_board {}
+
+    #[instantiate_tests(<crate::board::BinaryBoard>)]
+    mod binary_board {}
+
+    #[instantiate_tests(<crate::board::WideBinaryBoard>)]
+    mod wide_binary_board {}
+}
@@ -0,0 +1,561 @@
+//! Text format for puzzles, so that they can be edited without recompiling.
+//!
+//! A puzzle is written in one of two ways. Lines starting with `#` are comments, and empty lines
+//! are ignored in both.
+//!
+//! As a grid of 5 rows of 10 cells, with a letter per color (see `color_letter`) and `.` for
+//! the empty cells. Missing cells at the end of a row, or missing rows, are empty.
+//!
+//! ```text
+//! ..........
+//! YY..B...G.
+//! YY..B...GG
+//! Y...B...G.
+//! Y...BB....
+//! ```
+//!
+//! As a list of pieces, one per line: `color face orientation cell`, where the cell is the index
+//! of the top-left corner, from 0 to 49.
+//!
+//! ```text
+//! yellow B up 10
+//! blue A up 14
+//! green A up 18
+//! ```
+//!
+//! With the pieces of a `PieceSet`, only the list format can be used, with the names of the
+//! pieces instead of the colors: see `parse_list_with`.
+
+use crate::board::{placement_mask, placements, BoardGeometry, HEIGHT, NUM_CELLS, WIDTH};
+use crate::pieces::*;
+use crate::solver::COLOR_LIST;
+use std::fmt;
+
+/// What is wrong with a puzzle file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnknownColor(String),
+    UnknownFace(String),
+    UnknownOrientation(String),
+    InvalidCell(String),
+    /// A line of the list format doesn't have the 4 fields.
+    WrongFieldCount(usize),
+    /// The piece, or the grid cell, doesn't fit on the board.
+    OutOfBounds,
+    /// The piece covers a cell already covered by the piece of the given color.
+    Overlap(Color),
+    /// The color was already used by another piece.
+    DuplicateColor(Color),
+    /// The cells of the color in the grid don't form a piece.
+    NotAPiece(Color),
+    /// No piece of the `PieceSet` has this name.
+    UnknownPiece(String),
+    /// The piece covers a cell already covered by the piece of the `PieceSet` with this name.
+    OverlapsPiece(String),
+    /// The piece of the `PieceSet` with this name was already used.
+    DuplicatePiece(String),
+}
+
+/// An error in a puzzle file, with the position where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The line, starting at 1.
+    pub line: usize,
+    /// The column, starting at 1.
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnknownColor(s) => write!(f, "unknown color '{}'", s),
+            ParseErrorKind::UnknownFace(s) => write!(f, "unknown face '{}'", s),
+            ParseErrorKind::UnknownOrientation(s) => write!(f, "unknown orientation '{}'", s),
+            ParseErrorKind::InvalidCell(s) => write!(f, "invalid cell '{}'", s),
+            ParseErrorKind::WrongFieldCount(n) => {
+                write!(
+                    f,
+                    "expected 'color face orientation cell', got {} fields",
+                    n
+                )
+            }
+            ParseErrorKind::OutOfBounds => write!(f, "out of the board"),
+            ParseErrorKind::Overlap(c) => write!(f, "overlaps the {} piece", color_name(*c)),
+            ParseErrorKind::DuplicateColor(c) => {
+                write!(f, "the {} piece is already used", color_name(*c))
+            }
+            ParseErrorKind::NotAPiece(c) => {
+                write!(f, "the {} cells don't form a piece", color_name(*c))
+            }
+            ParseErrorKind::UnknownPiece(s) => write!(f, "unknown piece '{}'", s),
+            ParseErrorKind::OverlapsPiece(s) => write!(f, "overlaps the {} piece", s),
+            ParseErrorKind::DuplicatePiece(s) => write!(f, "the {} piece is already used", s),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The letter of the color in the grid format.
+pub fn color_letter(color: Color) -> char {
+    match color {
+        Color::Yellow => 'Y',
+        Color::Orange => 'O',
+        Color::Red => 'R',
+        Color::Pink => 'P',
+        Color::LightGreen => 'L',
+        Color::Green => 'G',
+        Color::LightBlue => 'C',
+        Color::Blue => 'B',
+        Color::DeepBlue => 'D',
+        Color::Purple => 'V',
+    }
+}
+
+/// The name of the color in the list format.
+pub fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::Yellow => "yellow",
+        Color::Orange => "orange",
+        Color::Red => "red",
+        Color::Pink => "pink",
+        Color::LightGreen => "light-green",
+        Color::Green => "green",
+        Color::LightBlue => "light-blue",
+        Color::Blue => "blue",
+        Color::DeepBlue => "deep-blue",
+        Color::Purple => "purple",
+    }
+}
+
+/// The name of the orientation in the list format.
+pub fn orientation_name(orientation: Orientation) -> &'static str {
+    match orientation {
+        Orientation::Up => "up",
+        Orientation::Right => "right",
+        Orientation::Down => "down",
+        Orientation::Left => "left",
+    }
+}
+
+/// Parse a color from its letter or its name, ignoring the case.
+pub fn parse_color(s: &str) -> Option<Color> {
+    COLOR_LIST.iter().cloned().find(|&c| {
+        s.eq_ignore_ascii_case(color_name(c))
+            || (s.len() == 1 && s.eq_ignore_ascii_case(&color_letter(c).to_string()))
+    })
+}
+
+pub(crate) fn parse_face(s: &str) -> Option<Face> {
+    match s {
+        "A" | "a" => Some(Face::A),
+        "B" | "b" => Some(Face::B),
+        _ => None,
+    }
+}
+
+fn parse_orientation(s: &str) -> Option<Orientation> {
+    [
+        Orientation::Up,
+        Orientation::Right,
+        Orientation::Down,
+        Orientation::Left,
+    ]
+    .iter()
+    .cloned()
+    .find(|&o| s.eq_ignore_ascii_case(orientation_name(o)))
+}
+
+/// The lines with content, along with their line number.
+pub(crate) fn content_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_end()))
+        .filter(|(_, line)| !line.trim_start().is_empty() && !line.trim_start().starts_with('#'))
+}
+
+/// The whitespace-separated fields of the line, with their column.
+pub(crate) fn fields(line: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    let mut start = None;
+    for (column, (i, ch)) in line.char_indices().enumerate() {
+        match (start, ch.is_whitespace()) {
+            (None, false) => start = Some((column + 1, i)),
+            (Some((column, s)), true) => {
+                fields.push((column, &line[s..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((column, s)) = start {
+        fields.push((column, &line[s..]));
+    }
+    fields
+}
+
+/// Parse a puzzle in either format. The format is chosen from the first line with content: a
+/// single field is a grid row.
+pub fn parse_puzzle(text: &str) -> Result<Vec<PlacedPiece>, ParseError> {
+    match content_lines(text).next() {
+        Some((_, line)) if fields(line).len() == 1 => parse_grid(text),
+        _ => parse_list(text),
+    }
+}
+
+/// Parse a puzzle in the list format.
+pub fn parse_list(text: &str) -> Result<Vec<PlacedPiece>, ParseError> {
+    parse_list_impl(text, None)
+}
+
+/// Parse a puzzle in the list format, made of the pieces of the set: each line starts with the
+/// name of a piece instead of a color.
+pub fn parse_list_with(text: &str, set: &PieceSet) -> Result<Vec<PlacedPiece>, ParseError> {
+    parse_list_impl(text, Some(set))
+}
+
+/// Parse a puzzle in the list format, with the standard pieces or the ones of the `set`.
+fn parse_list_impl(text: &str, set: Option<&PieceSet>) -> Result<Vec<PlacedPiece>, ParseError> {
+    let parse_name = |name: &str| match set {
+        None => parse_color(name).ok_or_else(|| ParseErrorKind::UnknownColor(name.to_owned())),
+        Some(set) => set
+            .index_of(name)
+            .map(|i| set.piece(i).color())
+            .ok_or_else(|| ParseErrorKind::UnknownPiece(name.to_owned())),
+    };
+    let mask = |piece: PlacedPiece| match set {
+        None => placement_mask(piece),
+        Some(set) => set
+            .piece_cells(&BoardGeometry::STANDARD, piece)
+            .map(|cells| cells.iter().fold(0, |mask, &cell| mask | 1 << cell)),
+    };
+    let piece_name = |color: Color| set.map(|set| set.name(color as u8).to_owned());
+    let mut pieces = Vec::new();
+    let mut cells: [Option<Color>; NUM_CELLS as usize] = [None; NUM_CELLS as usize];
+    for (line_number, line) in content_lines(text) {
+        let error = |column, kind| ParseError {
+            line: line_number,
+            column,
+            kind,
+        };
+        let fields = fields(line);
+        if fields.len() != 4 {
+            return Err(error(1, ParseErrorKind::WrongFieldCount(fields.len())));
+        }
+        let (color_column, color) = fields[0];
+        let color = parse_name(color).map_err(|kind| error(color_column, kind))?;
+        let (column, face) = fields[1];
+        let face = parse_face(face)
+            .ok_or_else(|| error(column, ParseErrorKind::UnknownFace(face.to_owned())))?;
+        let (column, orientation) = fields[2];
+        let orientation = parse_orientation(orientation).ok_or_else(|| {
+            error(
+                column,
+                ParseErrorKind::UnknownOrientation(orientation.to_owned()),
+            )
+        })?;
+        let (cell_column, cell) = fields[3];
+        let top_left: u8 = cell
+            .parse()
+            .map_err(|_| error(cell_column, ParseErrorKind::InvalidCell(cell.to_owned())))?;
+        if pieces
+            .iter()
+            .any(|p: &PlacedPiece| p.piece.color() == color)
+        {
+            let kind = match piece_name(color) {
+                None => ParseErrorKind::DuplicateColor(color),
+                Some(name) => ParseErrorKind::DuplicatePiece(name),
+            };
+            return Err(error(color_column, kind));
+        }
+        let piece = PlacedPiece {
+            piece: Piece::new()
+                .with_color(color)
+                .with_face(face)
+                .with_orientation(orientation),
+            top_left,
+        };
+        let mask = if top_left < NUM_CELLS {
+            mask(piece)
+        } else {
+            None
+        }
+        .ok_or_else(|| error(cell_column, ParseErrorKind::OutOfBounds))?;
+        for (i, cell) in cells.iter_mut().enumerate() {
+            if mask & (1 << i) != 0 {
+                if let Some(other) = *cell {
+                    let kind = match piece_name(other) {
+                        None => ParseErrorKind::Overlap(other),
+                        Some(name) => ParseErrorKind::OverlapsPiece(name),
+                    };
+                    return Err(error(cell_column, kind));
+                }
+                *cell = Some(color);
+            }
+        }
+        pieces.push(piece);
+    }
+    Ok(pieces)
+}
+
+/// Parse a puzzle in the grid format. The pieces are returned in the order of their top-left
+/// corner.
+///
+/// Some faces look the same in two orientations: they are returned in the first one.
+pub fn parse_grid(text: &str) -> Result<Vec<PlacedPiece>, ParseError> {
+    let mut masks = [0u64; 10];
+    // Where each color was first seen, to report errors.
+    let mut positions = [(0, 0); 10];
+    for (row, (line_number, line)) in content_lines(text).enumerate() {
+        for (column, ch) in line.chars().enumerate() {
+            if ch == '.' {
+                continue;
+            }
+            let error = |kind| ParseError {
+                line: line_number,
+                column: column + 1,
+                kind,
+            };
+            let color = parse_color(&ch.to_string())
+                .ok_or_else(|| error(ParseErrorKind::UnknownColor(ch.to_string())))?;
+            if row >= HEIGHT as usize || column >= WIDTH as usize {
+                return Err(error(ParseErrorKind::OutOfBounds));
+            }
+            if masks[color as usize] == 0 {
+                positions[color as usize] = (line_number, column + 1);
+            }
+            masks[color as usize] |= 1 << (row * WIDTH as usize + column);
+        }
+    }
+    let mut pieces = Vec::new();
+    for &color in COLOR_LIST.iter() {
+        let mask = masks[color as usize];
+        if mask == 0 {
+            continue;
+        }
+        let placed = (0..NUM_CELLS).find_map(|top_left| {
+            placements(top_left, color)
+                .iter()
+                .find(|p| p.mask == mask)
+                .map(|p| PlacedPiece {
+                    piece: p.piece(),
+                    top_left,
+                })
+        });
+        match placed {
+            Some(placed) => pieces.push(placed),
+            None => {
+                let (line, column) = positions[color as usize];
+                return Err(ParseError {
+                    line,
+                    column,
+                    kind: ParseErrorKind::NotAPiece(color),
+                });
+            }
+        }
+    }
+    pieces.sort_by_key(|p| p.top_left);
+    Ok(pieces)
+}
+
+/// Write the pieces in the list format, in order.
+pub fn write_list(pieces: &[PlacedPiece]) -> String {
+    write_list_impl(pieces, color_name)
+}
+
+/// Write the pieces of the set in the list format, in order, see `parse_list_with`.
+pub fn write_list_with(pieces: &[PlacedPiece], set: &PieceSet) -> String {
+    write_list_impl(pieces, |color| set.name(color as u8))
+}
+
+fn write_list_impl<'a, N: Fn(Color) -> &'a str>(pieces: &[PlacedPiece], name: N) -> String {
+    pieces
+        .iter()
+        .map(|p| {
+            format!(
+                "{} {:?} {} {}\n",
+                name(p.piece.color()),
+                p.piece.face(),
+                orientation_name(p.piece.orientation()),
+                p.top_left
+            )
+        })
+        .collect()
+}
+
+/// Write the pieces in the grid format, or None if they don't fit on the board together.
+pub fn write_grid(pieces: &[PlacedPiece]) -> Option<String> {
+    let mut cells = ['.'; NUM_CELLS as usize];
+    for p in pieces {
+        let mask = if p.top_left < NUM_CELLS {
+            placement_mask(*p)?
+        } else {
+            return None;
+        };
+        for (i, cell) in cells.iter_mut().enumerate() {
+            if mask & (1 << i) != 0 {
+                if *cell != '.' {
+                    return None;
+                }
+                *cell = color_letter(p.piece.color());
+            }
+        }
+    }
+    Some(
+        cells
+            .chunks(WIDTH as usize)
+            .map(|row| row.iter().chain(std::iter::once(&'\n')).collect::<String>())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::binary_board::BinaryBoard;
+    use crate::puzzles::*;
+    use crate::solver::solve;
+
+    fn error_at(
+        line: usize,
+        column: usize,
+        kind: ParseErrorKind,
+    ) -> Result<Vec<PlacedPiece>, ParseError> {
+        Err(ParseError { line, column, kind })
+    }
+
+    #[test]
+    fn list_round_trip() {
+        let text = write_list(&*PIECES_49);
+        assert_eq!(text, "yellow B up 10\nblue A up 14\ngreen A up 18\n");
+        assert_eq!(parse_puzzle(&text).unwrap(), PIECES_49.to_vec());
+        assert_eq!(
+            parse_puzzle("# Puzzle 117\n\n  Light-Blue b Right 3\nD A left 34\n").unwrap(),
+            PIECES_117.to_vec()
+        );
+    }
+
+    #[test]
+    fn grid_round_trip() {
+        let grid = write_grid(&*PIECES_49).unwrap();
+        assert_eq!(parse_puzzle(&grid).unwrap(), PIECES_49.to_vec());
+
+        let solution = solve::<BinaryBoard>(&*PIECES_117).unwrap();
+        let grid = write_grid(&solution).unwrap();
+        assert!(!grid.contains('.'));
+        let parsed = parse_grid(&grid).unwrap();
+        assert_eq!(parsed.len(), 10);
+        assert_eq!(write_grid(&parsed).unwrap(), grid);
+
+        assert_eq!(write_grid(&[PIECES_49[0], PIECES_49[0]]), None);
+    }
+
+    #[test]
+    fn list_errors() {
+        assert_eq!(
+            parse_list("yellow B up 10\nblue A up 10\n"),
+            error_at(2, 11, ParseErrorKind::Overlap(Color::Yellow))
+        );
+        assert_eq!(
+            parse_list("yellow B up 10\n\nyellow A up 30\n"),
+            error_at(3, 1, ParseErrorKind::DuplicateColor(Color::Yellow))
+        );
+        assert_eq!(
+            parse_list("# Comment\n  blue A up 49\n"),
+            error_at(2, 13, ParseErrorKind::OutOfBounds)
+        );
+        assert_eq!(
+            parse_list("blue A up 200\n"),
+            error_at(1, 11, ParseErrorKind::OutOfBounds)
+        );
+        assert_eq!(
+            parse_list("blue A sideways 3\n"),
+            error_at(
+                1,
+                8,
+                ParseErrorKind::UnknownOrientation("sideways".to_owned())
+            )
+        );
+        assert_eq!(
+            parse_list("blue A up\n"),
+            error_at(1, 1, ParseErrorKind::WrongFieldCount(3))
+        );
+    }
+
+    #[test]
+    fn list_with_piece_set() {
+        let set = PieceSet::parse("stick 4 A B\nhook 2 A 0,1 B 0,1 1,1").unwrap();
+        let text = "stick B right 10\n# A comment\nhook A up 5\n";
+        let pieces = parse_list_with(text, &set).unwrap();
+        assert_eq!(
+            pieces,
+            vec![
+                PlacedPiece {
+                    piece: set
+                        .piece(0)
+                        .with_face(Face::B)
+                        .with_orientation(Orientation::Right),
+                    top_left: 10
+                },
+                PlacedPiece {
+                    piece: set.piece(1),
+                    top_left: 5
+                }
+            ]
+        );
+        assert_eq!(
+            write_list_with(&pieces, &set),
+            "stick B right 10\nhook A up 5\n"
+        );
+        // The hook covers cells 0, 1 and 10.
+        assert_eq!(
+            parse_list_with("hook A up 0\nstick A right 10\n", &set),
+            error_at(2, 15, ParseErrorKind::OverlapsPiece("hook".to_owned()))
+        );
+        assert_eq!(
+            parse_list_with("hook A up 0\nhook A up 20\n", &set),
+            error_at(2, 1, ParseErrorKind::DuplicatePiece("hook".to_owned()))
+        );
+        assert_eq!(
+            parse_list_with("yellow A up 0\n", &set),
+            error_at(1, 1, ParseErrorKind::UnknownPiece("yellow".to_owned()))
+        );
+        assert_eq!(
+            parse_list_with("stick A right 7\n", &set),
+            error_at(1, 15, ParseErrorKind::OutOfBounds)
+        );
+        assert_eq!(
+            parse_list_with("hook A up 0\nstick A right 10\n", &set)
+                .unwrap_err()
+                .to_string(),
+            "2:15: overlaps the hook piece"
+        );
+    }
+
+    #[test]
+    fn grid_errors() {
+        assert_eq!(
+            parse_grid("Y.........\n.Y........\n"),
+            error_at(1, 1, ParseErrorKind::NotAPiece(Color::Yellow))
+        );
+        assert_eq!(
+            parse_grid("..........X\n"),
+            error_at(1, 11, ParseErrorKind::UnknownColor("X".to_owned()))
+        );
+        assert_eq!(
+            parse_grid("..........Y\n"),
+            error_at(1, 11, ParseErrorKind::OutOfBounds)
+        );
+        assert_eq!(
+            parse_grid(".\n.\n.\n.\n.\nY\n"),
+            error_at(6, 1, ParseErrorKind::OutOfBounds)
+        );
+    }
+}
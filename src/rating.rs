@@ -0,0 +1,200 @@
+//! Difficulty rating of a puzzle, from metrics of the search.
+
+use crate::board::{placement_mask, BinaryBoard, PlacementError};
+use crate::hint::{board_state, forced_placement};
+use crate::pieces::*;
+use crate::puzzles::Level;
+use crate::solver::{check_pieces, count_solutions, solve_with_stats};
+
+/// The solutions of the relaxed puzzles are counted up to this limit.
+const RELAXED_LIMIT: u64 = 100;
+
+/// The lowest score of each level after Starter.
+///
+/// These are placeholders, not a calibration: only two puzzles of the booklet are transcribed,
+/// so the thresholds are only chosen to rate those at their level, and to rate generated puzzles
+/// harder when fewer pieces are given. They have to be fitted again on the whole booklet once it
+/// is in `puzzles`, and may change then.
+pub const LEVEL_THRESHOLDS: [(f64, Level); 4] = [
+    (5.0, Level::Junior),
+    (8.0, Level::Expert),
+    (14.0, Level::Master),
+    (18.0, Level::Wizard),
+];
+
+/// How hard a puzzle is.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rating {
+    /// The number of pieces given.
+    pub given_pieces: usize,
+    /// The number of nodes the solver visited to find the solution.
+    pub nodes: u64,
+    /// The average number of children of the nodes the solver expanded.
+    pub branching_factor: f64,
+    /// The number of pieces that can be placed one after the other because they are the only
+    /// way to fill a cell, or the only place left for a piece.
+    pub forced_moves: u32,
+    /// The number of solutions of the puzzles with one of the given pieces removed, summed.
+    /// Each one is counted up to 100.
+    pub relaxed_solutions: u64,
+    /// The overall difficulty: higher is harder.
+    pub score: f64,
+}
+
+impl Rating {
+    /// The level of the booklet with puzzles of this difficulty.
+    ///
+    /// This uses the placeholder `LEVEL_THRESHOLDS`, so it is only a rough estimate that may
+    /// change once the thresholds are fitted on the whole booklet.
+    pub fn level(&self) -> Level {
+        level_of(self.score)
+    }
+}
+
+/// The level of the booklet with puzzles of this score.
+fn level_of(score: f64) -> Level {
+    LEVEL_THRESHOLDS
+        .iter()
+        .rev()
+        .find(|(threshold, _)| score >= *threshold)
+        .map_or(Level::Starter, |(_, level)| *level)
+}
+
+/// The number of placements that can be deduced one after the other, starting from `pieces`.
+///
+/// A placement is forced when it's the only one covering an empty cell, or the only one left
+/// for a piece. The faces are ignored.
+pub fn forced_moves(pieces: &[PlacedPiece]) -> u32 {
+    let (mut filled, mut colors_left) = board_state(pieces);
+    let mut forced = 0;
+    while let Some(piece) = forced_placement(filled, colors_left) {
+        filled |= placement_mask(piece).unwrap();
+        colors_left.remove(piece.piece.color());
+        forced += 1;
+    }
+    forced
+}
+
+/// The summed number of solutions of the puzzles with one of the given pieces removed.
+fn relaxed_solutions(pieces: &[PlacedPiece]) -> u64 {
+    (0..pieces.len())
+        .map(|i| {
+            let relaxed: Vec<PlacedPiece> = pieces
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, p)| *p)
+                .collect();
+            count_solutions::<BinaryBoard>(&relaxed, Some(RELAXED_LIMIT))
+        })
+        .sum()
+}
+
+/// Rate the difficulty of the puzzle, or None if it has no solution. The given pieces are checked
+/// first, like with `try_solve`.
+pub fn rate(pieces: &[PlacedPiece]) -> Result<Option<Rating>, PlacementError> {
+    check_pieces::<BinaryBoard>(pieces)?;
+    let (solution, stats) = solve_with_stats::<BinaryBoard>(pieces);
+    if solution.is_none() {
+        return Ok(None);
+    }
+    let nodes = stats.nodes();
+    // Every node but the root is the child of an expanded node, and every node is expanded
+    // except the pruned ones and the solution.
+    let expanded = nodes.saturating_sub(stats.pruned() + 1).max(1);
+    let branching_factor = (nodes - 1) as f64 / expanded as f64;
+    let forced_moves = forced_moves(pieces);
+    let relaxed_solutions = relaxed_solutions(pieces);
+    let score = ((nodes + 1) as f64).log2() + ((relaxed_solutions + 1) as f64).log2() / 2.0
+        - forced_moves as f64;
+    Ok(Some(Rating {
+        given_pieces: pieces.len(),
+        nodes,
+        branching_factor,
+        forced_moves,
+        relaxed_solutions,
+        score,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{generate, GeneratorOptions};
+    use crate::puzzles::*;
+    use crate::solver::solve;
+
+    #[test]
+    fn rate_catalog() {
+        for puzzle in catalog() {
+            let rating = rate(puzzle.pieces).unwrap().unwrap();
+            assert_eq!(rating.given_pieces, puzzle.pieces.len());
+            assert_eq!(rating.level(), puzzle.level, "Puzzle {}", puzzle.number);
+        }
+        let rating = rate(&*PIECES_49).unwrap().unwrap();
+        assert_eq!(rating.nodes, 41);
+        assert_eq!(rating.forced_moves, 0);
+        assert_eq!(rating.relaxed_solutions, 80);
+    }
+
+    #[test]
+    fn forced_moves_from_solution() {
+        let solution = solve::<BinaryBoard>(&*PIECES_117).unwrap();
+        // With a single piece missing, its placement is forced.
+        assert_eq!(forced_moves(&solution[..9]), 1);
+        let rating = rate(&solution[..8]).unwrap().unwrap();
+        assert_eq!(rating.forced_moves, 2);
+        assert_eq!(rating.level(), Level::Starter);
+    }
+
+    #[test]
+    fn rate_unsolvable() {
+        let piece = |color, top_left| PlacedPiece {
+            piece: Piece::new()
+                .with_color(color)
+                .with_face(Face::A)
+                .with_orientation(Orientation::Up),
+            top_left,
+        };
+        let pieces = [piece(Color::Yellow, 0), piece(Color::Blue, 2)];
+        assert_eq!(solve::<BinaryBoard>(&pieces), None);
+        assert_eq!(rate(&pieces), Ok(None));
+        let overlap = [piece(Color::Yellow, 0), piece(Color::Blue, 0)];
+        assert_eq!(
+            rate(&overlap),
+            Err(PlacementError::Overlap {
+                cell: 0,
+                color: Color::Yellow
+            })
+        );
+    }
+
+    /// The booklet only has a couple of transcribed puzzles, so check the ratings on generated
+    /// ones too: the fewer pieces are given, the harder the puzzle.
+    #[test]
+    fn rate_generated() {
+        let median_score = |num_given| {
+            let mut scores: Vec<f64> = (0..5)
+                .map(|seed| {
+                    let options = GeneratorOptions::new()
+                        .with_seed(seed)
+                        .with_num_given(num_given);
+                    let puzzle = generate(&options).unwrap();
+                    rate(&puzzle.pieces).unwrap().unwrap().score
+                })
+                .collect();
+            scores.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            scores[scores.len() / 2]
+        };
+        let scores: Vec<f64> = [8, 6, 4, 3, 2].iter().map(|&n| median_score(n)).collect();
+        for pair in scores.windows(2) {
+            assert!(pair[0] < pair[1], "{:?}", scores);
+        }
+        let levels: Vec<Level> = scores.iter().map(|&score| level_of(score)).collect();
+        for pair in levels.windows(2) {
+            assert!(pair[0] <= pair[1], "{:?}", levels);
+        }
+        assert!(levels[0] < levels[levels.len() - 1], "{:?}", levels);
+    }
+}
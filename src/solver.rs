@@ -1,7 +1,25 @@
-use crate::board::Board;
+use crate::board::{
+    for_each_empty_region, num_balls, placement_mask, placements, Board, Placement, PlacementError,
+    PlacementTable,
+};
 use crate::pieces::*;
+use options::BudgetCounter;
+use std::marker::PhantomData;
+use std::time::Instant;
 
-const COLOR_LIST: [Color; 10] = [
+pub mod dlx;
+pub mod options;
+pub mod parallel;
+pub mod stats;
+
+pub use dlx::{DlxSolver, GeometrySolver};
+pub use options::{FaceRule, Pruning, SolveOptions, SolveOutcome};
+pub use parallel::{
+    count_solutions_parallel, solve_all_parallel, solve_parallel, solve_parallel_with_options,
+};
+pub use stats::SearchStats;
+
+pub(crate) const COLOR_LIST: [Color; 10] = [
     Color::Yellow,
     Color::Orange,
     Color::Red,
@@ -14,18 +32,32 @@ const COLOR_LIST: [Color; 10] = [
     Color::Purple,
 ];
 
-const FACE_LIST: [Face; 2] = [Face::A, Face::B];
-
-const ORIENTATION_LIST: [Orientation; 4] = [
-    Orientation::Up,
-    Orientation::Right,
-    Orientation::Down,
-    Orientation::Left,
-];
-
 pub trait IterationCounter {
     fn increment(&mut self);
     fn get(&self) -> u64;
+    /// Whether the search should stop now, e.g. because it ran out of budget.
+    #[inline]
+    fn should_abort(&self) -> bool {
+        false
+    }
+    /// Called when entering a search node, with the number of pieces on the board.
+    #[inline]
+    fn record_node(&mut self, _depth: u8) {}
+    /// Called when a node is pruned by `Board::check_common_failures`.
+    #[inline]
+    fn record_pruned<B: Board>(&mut self, _board: &B) {}
+    /// Called when a node is pruned because the pieces left can't cover the empty cells.
+    #[inline]
+    fn record_cell_count_mismatch(&mut self) {}
+    /// Called when a node is pruned because a region of empty cells can't be filled.
+    #[inline]
+    fn record_dead_region(&mut self) {}
+    /// Called when a face is skipped because of the face policy.
+    #[inline]
+    fn record_face_rejection(&mut self) {}
+    /// Called when a piece doesn't fit on the board.
+    #[inline]
+    fn record_placement_failure(&mut self) {}
 }
 
 struct NoOpIterationCounter {}
@@ -39,31 +71,19 @@ impl IterationCounter for NoOpIterationCounter {
     }
 }
 
-struct SimpleIterationCounter(u64);
-
-impl IterationCounter for SimpleIterationCounter {
-    #[inline]
-    fn increment(&mut self) {
-        self.0 += 1;
-        if self.0 > 5000000 {
-            panic!("Too many iterations!");
-        }
-    }
-    #[inline]
-    fn get(&self) -> u64 {
-        self.0
-    }
-}
-
 /// A set of colors, starting full, and getting progressively empty.
-#[derive(Copy, Clone)]
-struct ColorSet(pub u16);
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ColorSet(pub u16);
 
 impl ColorSet {
     pub fn full() -> Self {
         ColorSet(!0)
     }
 
+    pub fn from_colors(colors: &[Color]) -> Self {
+        ColorSet(colors.iter().fold(0, |acc, &c| acc | 1 << (c as u8)))
+    }
+
     pub fn remove(&mut self, c: Color) -> bool {
         let res = self.contains(c);
         self.0 &= !(1 << (c as u8));
@@ -74,20 +94,49 @@ impl ColorSet {
         (self.0 & 1 << (c as u8)) != 0
     }
 
+    /// The number of colors in the set.
+    pub fn len(&self) -> u8 {
+        (self.0 & ((1 << COLOR_LIST.len()) - 1)).count_ones() as u8
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn without_color(mut self, c: Color) -> Self {
         self.remove(c);
         self
     }
 }
 
-trait FacePolicy: Copy {
+impl Default for ColorSet {
+    fn default() -> Self {
+        Self::full()
+    }
+}
+
+/// A rule on how many pieces can be placed on each face.
+///
+/// The policy is updated with each piece placed, and asked before placing the next one.
+pub trait FacePolicy: Copy {
+    /// Whether a piece can be placed on the face `f`.
     fn can_add_face(&self, f: Face) -> bool;
+    /// The policy after placing a piece on the face `f`.
     fn with_face(self, f: Face) -> Self;
+    /// The policy for a board with the given pieces already on it.
     fn from_placed_pieces(pieces: &[PlacedPiece]) -> Self;
+    /// The minimum and maximum number of B faces among the next `num_pieces` pieces.
+    fn face_b_range(&self, num_pieces: u8) -> (u8, u8);
+    /// The face with more pieces than allowed, if any. This can only happen with the pieces
+    /// given to `from_placed_pieces`.
+    fn exceeded_face(&self) -> Option<Face> {
+        None
+    }
 }
 
-#[derive(Copy, Clone)]
-struct TenPieceFacePolicy {
+/// The rule of the ten-piece puzzles: 6 pieces on face A, 4 on face B.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TenPieceFacePolicy {
     num_face_a: u8,
     num_face_b: u8,
 }
@@ -123,10 +172,25 @@ impl FacePolicy for TenPieceFacePolicy {
             num_face_b,
         }
     }
+    fn face_b_range(&self, num_pieces: u8) -> (u8, u8) {
+        let max_face_a = num_pieces.min(6u8.saturating_sub(self.num_face_a));
+        let max_face_b = num_pieces.min(4u8.saturating_sub(self.num_face_b));
+        (num_pieces - max_face_a, max_face_b)
+    }
+    fn exceeded_face(&self) -> Option<Face> {
+        if self.num_face_a > 6 {
+            Some(Face::A)
+        } else if self.num_face_b > 4 {
+            Some(Face::B)
+        } else {
+            None
+        }
+    }
 }
 
-#[derive(Copy, Clone)]
-struct NinePieceFacePolicy {
+/// At most 4 pieces on face A, for challenges with nine pieces.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct NinePieceFacePolicy {
     num_face_a: u8,
 }
 
@@ -147,103 +211,786 @@ impl FacePolicy for NinePieceFacePolicy {
         let num_face_a = pieces.iter().filter(|p| p.piece.face() == Face::A).count() as u8;
         NinePieceFacePolicy { num_face_a }
     }
+    fn face_b_range(&self, num_pieces: u8) -> (u8, u8) {
+        let max_face_a = num_pieces.min(4u8.saturating_sub(self.num_face_a));
+        (num_pieces - max_face_a, num_pieces)
+    }
+    fn exceeded_face(&self) -> Option<Face> {
+        if self.num_face_a > 4 {
+            Some(Face::A)
+        } else {
+            None
+        }
+    }
 }
 
-fn solve_rec<B: Board + Copy, C: IterationCounter, F: FacePolicy>(
+/// No constraint on the faces: any piece can be placed on either face.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct NoFacePolicy;
+
+impl FacePolicy for NoFacePolicy {
+    #[inline]
+    fn can_add_face(&self, _f: Face) -> bool {
+        true
+    }
+    #[inline]
+    fn with_face(self, _f: Face) -> Self {
+        self
+    }
+    fn from_placed_pieces(_pieces: &[PlacedPiece]) -> Self {
+        NoFacePolicy
+    }
+    fn face_b_range(&self, num_pieces: u8) -> (u8, u8) {
+        (0, num_pieces)
+    }
+}
+
+/// Where the search takes the shapes of the pieces from.
+pub(crate) trait Shapes: Copy {
+    /// Like `board::placements`.
+    fn placements(&self, top_left: u8, color: Color) -> &[Placement];
+    /// The number of balls of the piece.
+    fn num_balls(&self, piece: Piece) -> u8;
+    /// The placement of the piece, or None if it doesn't fit on the board.
+    fn placement(&self, piece: PlacedPiece) -> Option<Placement>;
+}
+
+/// The standard pieces, from the tables of the build script.
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct StandardShapes;
+
+impl Shapes for StandardShapes {
+    #[inline]
+    fn placements(&self, top_left: u8, color: Color) -> &[Placement] {
+        placements(top_left, color)
+    }
+    #[inline]
+    fn num_balls(&self, piece: Piece) -> u8 {
+        num_balls(piece)
+    }
+    fn placement(&self, piece: PlacedPiece) -> Option<Placement> {
+        placement_mask(piece).map(|mask| Placement {
+            piece: piece.piece.as_byte(),
+            mask,
+        })
+    }
+}
+
+/// The pieces of a `PieceSet`.
+impl Shapes for &PlacementTable {
+    #[inline]
+    fn placements(&self, top_left: u8, color: Color) -> &[Placement] {
+        PlacementTable::placements(self, top_left, color)
+    }
+    #[inline]
+    fn num_balls(&self, piece: Piece) -> u8 {
+        PlacementTable::num_balls(self, piece)
+    }
+    fn placement(&self, piece: PlacedPiece) -> Option<Placement> {
+        PlacementTable::placement(self, piece)
+    }
+}
+
+/// The state of the search at a node: the board, and what is left to place on it.
+#[derive(Copy, Clone)]
+struct SearchNode<B: Board, F: FacePolicy, S: Shapes = StandardShapes> {
     board: B,
     colors_left: ColorSet,
+    /// The first cell that might be empty.
     empty_index_lower_bound: u8,
     face_policy: F,
+    shapes: S,
+    /// Whether every solution uses all the pieces left, see `can_leave_pieces_out`.
+    all_pieces_needed: bool,
+}
+
+impl<B: Board, F: FacePolicy> SearchNode<B, F> {
+    /// The root of the search, with the given pieces placed on an empty board, or None if they
+    /// already break the face policy. The pieces must fit on the board and have different colors.
+    fn root(pieces: &[PlacedPiece]) -> Option<Self> {
+        let mut colors_left = ColorSet::default();
+        let mut board = B::default();
+        for p in pieces {
+            let was_present = colors_left.remove(p.piece.color());
+            debug_assert!(was_present);
+            debug_assert!(board.can_place_piece(*p));
+            board = board.with_piece(*p);
+        }
+        let root = Self::with_board(board, colors_left, pieces, StandardShapes);
+        if root.face_policy.exceeded_face().is_some() {
+            return None;
+        }
+        Some(root)
+    }
+
+    /// Like `root`, but only the `colors` are available, and returns None if the pieces overlap,
+    /// don't fit on the board, use a color that isn't available or break the face policy.
+    fn try_root(pieces: &[PlacedPiece], colors: ColorSet) -> Option<Self> {
+        SearchNode::try_root_with_shapes(pieces, colors, StandardShapes)
+    }
+}
+
+impl<B: Board, F: FacePolicy, S: Shapes> SearchNode<B, F, S> {
+    /// Like `try_root`, with the pieces of `shapes`.
+    fn try_root_with_shapes(pieces: &[PlacedPiece], colors: ColorSet, shapes: S) -> Option<Self> {
+        let mut colors_left = colors;
+        let mut board = B::default();
+        for p in pieces {
+            if !colors_left.remove(p.piece.color()) {
+                return None;
+            }
+            board = board.maybe_with_placement(&shapes.placement(*p)?, p.top_left)?;
+        }
+        let root = Self::with_board(board, colors_left, pieces, shapes);
+        if root.face_policy.exceeded_face().is_some() {
+            return None;
+        }
+        Some(root)
+    }
+
+    fn with_board(board: B, colors_left: ColorSet, pieces: &[PlacedPiece], shapes: S) -> Self {
+        let mut node = SearchNode {
+            board,
+            colors_left,
+            empty_index_lower_bound: 0,
+            face_policy: F::from_placed_pieces(pieces),
+            shapes,
+            all_pieces_needed: false,
+        };
+        // If the root can't be filled without all the pieces, neither can its descendants.
+        node.all_pieces_needed = !node.can_leave_pieces_out();
+        node
+    }
+
+    /// Check whether the board can't be completed, according to the enabled pruning rules.
+    #[inline]
+    fn is_dead_end<C: IterationCounter>(&self, pruning: Pruning, counter: &mut C) -> bool {
+        if pruning.hole_patterns && self.board.check_common_failures() {
+            counter.record_pruned(&self.board);
+            return true;
+        }
+        if pruning.cell_count && !self.has_matching_cell_count() {
+            counter.record_cell_count_mismatch();
+            return true;
+        }
+        if pruning.regions && has_dead_region(&self.board, self.colors_left, self.shapes) {
+            counter.record_dead_region();
+            return true;
+        }
+        false
+    }
+}
+
+impl<B: Board, F: FacePolicy, S: Shapes> SearchNode<B, F, S> {
+    /// The number of balls of each face of the pieces left, and the number of pieces left.
+    fn sizes_left(&self) -> ([(u8, u8); 10], u8) {
+        let mut sizes = [(0, 0); 10];
+        let mut num_pieces = 0;
+        let mut piece = Piece::new();
+        for &c in COLOR_LIST.iter() {
+            if self.colors_left.contains(c) {
+                piece.set_color(c);
+                sizes[num_pieces as usize] = (
+                    self.shapes.num_balls(piece.with_face(Face::A)),
+                    self.shapes.num_balls(piece.with_face(Face::B)),
+                );
+                num_pieces += 1;
+            }
+        }
+        (sizes, num_pieces)
+    }
+
+    /// Whether some of the pieces left, but not all of them, can cover exactly the empty cells,
+    /// with the faces allowed by the face policy. The search accepts a full board with pieces
+    /// left over, so only without such a subset must the pieces left cover the empty cells
+    /// exactly.
+    fn can_leave_pieces_out(&self) -> bool {
+        let empty_cells = self.board.empty_cells().count_ones();
+        let (sizes, num_pieces) = self.sizes_left();
+        let (min_face_b, max_face_b) = self.face_policy.face_b_range(num_pieces);
+        let max_face_a = num_pieces - min_face_b;
+        // `sums[a][b]` has bit `n` set iff some `a` pieces on face A and `b` on face B cover
+        // `n` cells.
+        let mut sums = [[0u128; 11]; 11];
+        sums[0][0] = 1;
+        for &(size_a, size_b) in &sizes[..num_pieces as usize] {
+            for a in (0..=max_face_a as usize).rev() {
+                for b in (0..=max_face_b as usize).rev() {
+                    let sum = sums[a][b];
+                    if a < max_face_a as usize {
+                        sums[a + 1][b] |= sum.checked_shl(size_a as u32).unwrap_or(0);
+                    }
+                    if b < max_face_b as usize {
+                        sums[a][b + 1] |= sum.checked_shl(size_b as u32).unwrap_or(0);
+                    }
+                }
+            }
+        }
+        (0..=max_face_a as usize).any(|a| {
+            (0..=max_face_b as usize)
+                .filter(|&b| a + b < num_pieces as usize)
+                .any(|b| sums[a][b] & (1 << empty_cells) != 0)
+        })
+    }
+
+    /// Check that the pieces left can cover the empty cells, with the faces allowed by the face
+    /// policy. When all of them are needed, they must cover exactly the empty cells.
+    fn has_matching_cell_count(&self) -> bool {
+        let empty_cells = self.board.empty_cells().count_ones() as i16;
+        let (sizes, num_pieces) = self.sizes_left();
+        if !self.all_pieces_needed {
+            let max_cells: i16 = sizes[..num_pieces as usize]
+                .iter()
+                .map(|&(size_a, size_b)| size_a.max(size_b) as i16)
+                .sum();
+            return empty_cells <= max_cells;
+        }
+        let mut cells_a = 0i16;
+        // How many more cells each piece covers with its B face. Only home-made pieces can
+        // cover fewer.
+        let mut face_b_extra = [0i8; 10];
+        for (extra, &(size_a, size_b)) in face_b_extra.iter_mut().zip(&sizes[..num_pieces as usize])
+        {
+            *extra = size_b as i8 - size_a as i8;
+            cells_a += size_a as i16;
+        }
+        let extras = &mut face_b_extra[..num_pieces as usize];
+        extras.sort_unstable();
+        let (min_face_b, max_face_b) = self.face_policy.face_b_range(num_pieces);
+        // With `k` pieces on face B, the fewest extra cells come from the `k` smallest extras,
+        // and the most from the `k` largest.
+        let (mut min_extra, mut max_extra) = (i16::MAX, i16::MIN);
+        let (mut smallest, mut largest) = (0i16, 0i16);
+        for k in 0..=num_pieces {
+            if min_face_b <= k && k <= max_face_b {
+                min_extra = min_extra.min(smallest);
+                max_extra = max_extra.max(largest);
+            }
+            if k < num_pieces {
+                smallest += extras[k as usize] as i16;
+                largest += extras[(num_pieces - 1 - k) as usize] as i16;
+            }
+        }
+        cells_a + min_extra <= empty_cells && empty_cells <= cells_a + max_extra
+    }
+}
+
+/// The sizes of the regions that the colors left can fill, as a bitfield: bit `n` is set iff
+/// some of the pieces left can cover exactly `n` cells, whatever their face.
+fn fillable_sizes<S: Shapes>(colors_left: ColorSet, shapes: S) -> u64 {
+    let mut sizes = 1u64;
+    let mut piece = Piece::new();
+    for &c in COLOR_LIST.iter() {
+        if colors_left.contains(c) {
+            piece.set_color(c);
+            let size_a = shapes.num_balls(piece.with_face(Face::A));
+            let size_b = shapes.num_balls(piece.with_face(Face::B));
+            sizes |= (sizes << size_a) | (sizes << size_b);
+        }
+    }
+    sizes
+}
+
+/// Check whether one of the connected regions of empty cells can't be filled exactly by the
+/// pieces left. In particular, every region smaller than the smallest piece is dead.
+fn has_dead_region<B: Board, S: Shapes>(board: &B, colors_left: ColorSet, shapes: S) -> bool {
+    let fillable = fillable_sizes(colors_left, shapes);
+    !for_each_empty_region(board.empty_cells(), |region| {
+        fillable & (1 << region.count_ones()) != 0
+    })
+}
+
+/// Explore every filling of the board, calling `visitor` on each complete one.
+/// `placed` holds the pieces on the board so far, and is left unchanged when returning.
+/// Returns false if the search was stopped, either by the visitor or by the counter.
+fn search_rec<B: Board + Copy, C: IterationCounter, F: FacePolicy, S: Shapes, V>(
+    node: SearchNode<B, F, S>,
+    pruning: Pruning,
     counter: &mut C,
-) -> Option<Vec<PlacedPiece>> {
-    if board.check_common_failures() {
-        return None;
+    placed: &mut Vec<PlacedPiece>,
+    visitor: &mut V,
+) -> bool
+where
+    V: FnMut(&[PlacedPiece]) -> bool,
+{
+    counter.record_node(placed.len() as u8);
+    if node.is_dead_end(pruning, counter) {
+        return true;
     }
-    let index = board.first_empty_cell(empty_index_lower_bound);
+    let index = node.board.first_empty_cell(node.empty_index_lower_bound);
     if index.is_none() {
-        return Some(Vec::with_capacity(10));
+        return visitor(placed);
     }
     let index = index.unwrap();
-    let mut piece = PlacedPiece {
-        piece: Piece::new(),
-        top_left: index,
-    };
     for &c in COLOR_LIST.iter() {
-        if !colors_left.contains(c) {
+        if !node.colors_left.contains(c) {
             continue;
         }
-        let next_colors = colors_left.without_color(c);
-        piece.piece.set_color(c);
-        for &face in FACE_LIST.iter() {
-            if !face_policy.can_add_face(face) {
+        let next_colors = node.colors_left.without_color(c);
+        for placement in node.shapes.placements(index, c) {
+            let piece = placement.piece();
+            let face = piece.face();
+            if !node.face_policy.can_add_face(face) {
+                counter.record_face_rejection();
                 continue;
             }
-            piece.piece.set_face(face);
-            for &orientation in ORIENTATION_LIST.iter() {
-                piece.piece.set_orientation(orientation);
-                counter.increment();
-                if let Some(new_board) = board.maybe_with_piece(piece) {
-                    if let Some(mut pieces) = solve_rec(
-                        new_board,
-                        next_colors,
-                        index + 1,
-                        face_policy.with_face(face),
-                        counter,
-                    ) {
-                        pieces.push(piece);
-                        return Some(pieces);
-                    }
+            counter.increment();
+            if counter.should_abort() {
+                return false;
+            }
+            if let Some(new_board) = node.board.maybe_with_placement(placement, index) {
+                placed.push(PlacedPiece {
+                    piece,
+                    top_left: index,
+                });
+                let keep_going = search_rec(
+                    SearchNode {
+                        board: new_board,
+                        colors_left: next_colors,
+                        empty_index_lower_bound: index + 1,
+                        face_policy: node.face_policy.with_face(face),
+                        shapes: node.shapes,
+                        all_pieces_needed: node.all_pieces_needed,
+                    },
+                    pruning,
+                    counter,
+                    placed,
+                    visitor,
+                );
+                placed.pop();
+                if !keep_going {
+                    return false;
                 }
+            } else {
+                counter.record_placement_failure();
             }
         }
     }
-    None
+    true
+}
+
+/// Search the board for a solution, returning all the pieces on the board.
+fn solve_rec<B: Board, C: IterationCounter, F: FacePolicy, S: Shapes>(
+    node: SearchNode<B, F, S>,
+    pruning: Pruning,
+    counter: &mut C,
+    placed: &mut Vec<PlacedPiece>,
+) -> Option<Vec<PlacedPiece>> {
+    let mut solution = None;
+    search_rec(
+        node,
+        pruning,
+        counter,
+        placed,
+        &mut |pieces: &[PlacedPiece]| {
+            solution = Some(pieces.to_vec());
+            false
+        },
+    );
+    solution
+}
+
+/// Count the fillings of the board, without building the piece lists.
+/// The search stops as soon as `limit` solutions have been found.
+fn count_rec<B: Board, C: IterationCounter, F: FacePolicy, S: Shapes>(
+    node: SearchNode<B, F, S>,
+    pruning: Pruning,
+    counter: &mut C,
+    placed: &mut Vec<PlacedPiece>,
+    limit: u64,
+) -> u64 {
+    let mut count = 0;
+    if limit > 0 {
+        search_rec(
+            node,
+            pruning,
+            counter,
+            placed,
+            &mut |_: &[PlacedPiece]| {
+                count += 1;
+                count < limit
+            },
+        );
+    }
+    count
+}
+
+/// The stack of pieces on the board for the search, starting with the given ones.
+fn placed_pieces(pieces: &[PlacedPiece]) -> Vec<PlacedPiece> {
+    let mut placed = Vec::with_capacity(10);
+    placed.extend_from_slice(pieces);
+    placed
 }
 
 fn solve_impl<B: Board, C: IterationCounter, F: FacePolicy>(
     pieces: &[PlacedPiece],
     counter: &mut C,
 ) -> Option<Vec<PlacedPiece>> {
-    let mut colors_left = ColorSet::full();
-    let mut board = B::default();
-    for p in pieces {
-        debug_assert!(colors_left.remove(p.piece.color()));
-        debug_assert!(board.can_place_piece(*p));
-        board = board.with_piece(*p);
-    }
-    if let Some(mut pieces_solution) = solve_rec(
-        board,
-        colors_left,
-        0,
-        F::from_placed_pieces(pieces),
+    solve_rec(
+        SearchNode::<B, F>::root(pieces)?,
+        Pruning::default(),
         counter,
-    ) {
-        for p in pieces {
-            pieces_solution.push(*p);
-        }
-        Some(pieces_solution)
-    } else {
-        None
-    }
+        &mut placed_pieces(pieces),
+    )
 }
 
 pub fn solve<B: Board>(pieces: &[PlacedPiece]) -> Option<Vec<PlacedPiece>> {
+    solve_with_policy::<B, TenPieceFacePolicy>(pieces)
+}
+
+/// Like `solve`, with the face policy `F` instead of the ten-piece rule.
+pub fn solve_with_policy<B: Board, F: FacePolicy>(
+    pieces: &[PlacedPiece],
+) -> Option<Vec<PlacedPiece>> {
     let mut counter = NoOpIterationCounter {};
-    solve_impl::<B, NoOpIterationCounter, TenPieceFacePolicy>(pieces, &mut counter)
+    solve_impl::<B, NoOpIterationCounter, F>(pieces, &mut counter)
+}
+
+/// Call `visitor` on every solution of the puzzle, in search order.
+///
+/// Each solution starts with the given `pieces`, followed by the pieces found by the solver. The
+/// visitor returns whether to keep searching: returning false stops the enumeration.
+pub fn for_each_solution<B: Board, V>(pieces: &[PlacedPiece], visitor: V)
+where
+    V: FnMut(&[PlacedPiece]) -> bool,
+{
+    for_each_solution_with_policy::<B, TenPieceFacePolicy, V>(pieces, visitor)
+}
+
+/// Like `for_each_solution`, with the face policy `F` instead of the ten-piece rule.
+pub fn for_each_solution_with_policy<B: Board, F: FacePolicy, V>(
+    pieces: &[PlacedPiece],
+    mut visitor: V,
+) where
+    V: FnMut(&[PlacedPiece]) -> bool,
+{
+    if let Some(root) = SearchNode::<B, F>::root(pieces) {
+        search_rec(
+            root,
+            Pruning::default(),
+            &mut NoOpIterationCounter {},
+            &mut placed_pieces(pieces),
+            &mut visitor,
+        );
+    }
+}
+
+/// Like `for_each_solution_with_policy`, with the pieces of `shapes`. Invalid puzzles have no
+/// solution, like with `solve_with_options`.
+pub(crate) fn for_each_solution_with_shapes<B: Board, F: FacePolicy, S: Shapes, V>(
+    pieces: &[PlacedPiece],
+    shapes: S,
+    mut visitor: V,
+) where
+    V: FnMut(&[PlacedPiece]) -> bool,
+{
+    let root = SearchNode::<B, F, S>::try_root_with_shapes(pieces, ColorSet::full(), shapes);
+    if let Some(root) = root {
+        search_rec(
+            root,
+            Pruning::default(),
+            &mut NoOpIterationCounter {},
+            &mut placed_pieces(pieces),
+            &mut visitor,
+        );
+    }
+}
+
+/// Find every solution of the puzzle, instead of stopping at the first one.
+pub fn solve_all<B: Board>(pieces: &[PlacedPiece]) -> Vec<Vec<PlacedPiece>> {
+    let mut solutions = Vec::new();
+    for_each_solution::<B, _>(pieces, |solution| {
+        solutions.push(solution.to_vec());
+        true
+    });
+    solutions
+}
+
+/// Count the solutions of the puzzle.
+///
+/// If a `limit` is given, the search stops once that many solutions have been found, and the
+/// limit is returned.
+pub fn count_solutions<B: Board>(pieces: &[PlacedPiece], limit: Option<u64>) -> u64 {
+    count_solutions_with_policy::<B, TenPieceFacePolicy>(pieces, limit)
+}
+
+/// Like `count_solutions`, with the face policy `F` instead of the ten-piece rule.
+pub fn count_solutions_with_policy<B: Board, F: FacePolicy>(
+    pieces: &[PlacedPiece],
+    limit: Option<u64>,
+) -> u64 {
+    match SearchNode::<B, F>::root(pieces) {
+        Some(root) => count_rec(
+            root,
+            Pruning::default(),
+            &mut NoOpIterationCounter {},
+            &mut placed_pieces(pieces),
+            limit.unwrap_or(u64::MAX),
+        ),
+        None => 0,
+    }
+}
+
+/// Check that the pieces can start a puzzle: they fit on the board together, have different
+/// colors, and follow the ten-piece rule.
+pub fn check_pieces<B: Board>(pieces: &[PlacedPiece]) -> Result<(), PlacementError> {
+    B::try_from_placed_piece_list(pieces)?;
+    match TenPieceFacePolicy::from_placed_pieces(pieces).exceeded_face() {
+        Some(face) => Err(PlacementError::FaceQuotaExceeded(face)),
+        None => Ok(()),
+    }
+}
+
+/// Like `solve`, but the given pieces are checked first.
+pub fn try_solve<B: Board>(
+    pieces: &[PlacedPiece],
+) -> Result<Option<Vec<PlacedPiece>>, PlacementError> {
+    check_pieces::<B>(pieces)?;
+    Ok(solve::<B>(pieces))
+}
+
+/// Like `count_solutions`, but the given pieces are checked first.
+pub fn try_count_solutions<B: Board>(
+    pieces: &[PlacedPiece],
+    limit: Option<u64>,
+) -> Result<u64, PlacementError> {
+    check_pieces::<B>(pieces)?;
+    Ok(count_solutions::<B>(pieces, limit))
+}
+
+/// Check whether the puzzle has exactly one solution.
+/// This stops the search as soon as a second solution is found.
+pub fn is_unique<B: Board>(pieces: &[PlacedPiece]) -> bool {
+    count_solutions::<B>(pieces, Some(2)) == 1
+}
+
+/// A solving algorithm, so that the different backends can be used interchangeably.
+pub trait Solver {
+    /// Find a solution to the puzzle, made of the given `pieces` and the ones placed to fill the
+    /// board.
+    fn solve(&self, pieces: &[PlacedPiece]) -> Option<Vec<PlacedPiece>>;
+    /// Find every solution of the puzzle.
+    fn solve_all(&self, pieces: &[PlacedPiece]) -> Vec<Vec<PlacedPiece>>;
+    /// Count the solutions of the puzzle, stopping at `limit` if given.
+    fn count_solutions(&self, pieces: &[PlacedPiece], limit: Option<u64>) -> u64;
+}
+
+/// The backtracking solver, always filling the first empty cell from the top left.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct BacktrackingSolver<B: Board> {
+    board: PhantomData<B>,
+}
+
+impl<B: Board> Solver for BacktrackingSolver<B> {
+    fn solve(&self, pieces: &[PlacedPiece]) -> Option<Vec<PlacedPiece>> {
+        solve::<B>(pieces)
+    }
+
+    fn solve_all(&self, pieces: &[PlacedPiece]) -> Vec<Vec<PlacedPiece>> {
+        solve_all::<B>(pieces)
+    }
+
+    fn count_solutions(&self, pieces: &[PlacedPiece], limit: Option<u64>) -> u64 {
+        count_solutions::<B>(pieces, limit)
+    }
+}
+
+/// The backtracking solver on the standard board, with the pieces of a `PieceSet` instead of
+/// the standard ones.
+///
+/// A solution covers every cell of the board, each piece being used at most once. The faces
+/// follow `face_rule`, unconstrained by default like for `GeometrySolver`. The given pieces are
+/// checked: if they don't fit on the board together, the puzzle has no solution.
+///
+/// Only the boards placing the pieces by their mask can hold them: `BinaryBoard`,
+/// `WideBinaryBoard` and `DisplayBoard`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PieceSetSolver<B: Board> {
+    table: PlacementTable,
+    colors: ColorSet,
+    pub face_rule: FaceRule,
+    board: PhantomData<B>,
+}
+
+impl<B: Board> PieceSetSolver<B> {
+    pub fn new(pieces: &PieceSet) -> Self {
+        PieceSetSolver {
+            table: PlacementTable::new(pieces),
+            colors: pieces.colors(),
+            face_rule: FaceRule::Unconstrained,
+            board: PhantomData,
+        }
+    }
+
+    pub fn with_face_rule(mut self, face_rule: FaceRule) -> Self {
+        self.face_rule = face_rule;
+        self
+    }
+
+    /// Call `visitor` on every solution of the puzzle, until it returns false. Each solution
+    /// starts with the given `pieces`.
+    pub fn for_each_solution<V>(&self, pieces: &[PlacedPiece], visitor: V)
+    where
+        V: FnMut(&[PlacedPiece]) -> bool,
+    {
+        match self.face_rule {
+            FaceRule::TenPiece => self.search::<TenPieceFacePolicy, V>(pieces, visitor),
+            FaceRule::NinePiece => self.search::<NinePieceFacePolicy, V>(pieces, visitor),
+            FaceRule::Unconstrained => self.search::<NoFacePolicy, V>(pieces, visitor),
+        }
+    }
+
+    fn search<F: FacePolicy, V>(&self, pieces: &[PlacedPiece], mut visitor: V)
+    where
+        V: FnMut(&[PlacedPiece]) -> bool,
+    {
+        let root = SearchNode::<B, F, _>::try_root_with_shapes(pieces, self.colors, &self.table);
+        if let Some(root) = root {
+            // The hole patterns are only dead ends if no piece is smaller than the holes. The
+            // cell count only asks for an exact fit when no piece can be left over, see
+            // `Pruning::cell_count`.
+            let pruning = Pruning {
+                hole_patterns: self.table.min_balls() > 3,
+                ..Pruning::default()
+            };
+            search_rec(
+                root,
+                pruning,
+                &mut NoOpIterationCounter {},
+                &mut placed_pieces(pieces),
+                &mut visitor,
+            );
+        }
+    }
+}
+
+impl<B: Board> Solver for PieceSetSolver<B> {
+    fn solve(&self, pieces: &[PlacedPiece]) -> Option<Vec<PlacedPiece>> {
+        let mut solution = None;
+        self.for_each_solution(pieces, |pieces| {
+            solution = Some(pieces.to_vec());
+            false
+        });
+        solution
+    }
+
+    fn solve_all(&self, pieces: &[PlacedPiece]) -> Vec<Vec<PlacedPiece>> {
+        let mut solutions = Vec::new();
+        self.for_each_solution(pieces, |solution| {
+            solutions.push(solution.to_vec());
+            true
+        });
+        solutions
+    }
+
+    fn count_solutions(&self, pieces: &[PlacedPiece], limit: Option<u64>) -> u64 {
+        let limit = limit.unwrap_or(u64::MAX);
+        let mut count = 0;
+        if limit > 0 {
+            self.for_each_solution(pieces, |_| {
+                count += 1;
+                count < limit
+            });
+        }
+        count
+    }
+}
+
+/// Solve the puzzle within the limits given by the `options`.
+///
+/// Unlike `solve`, the given pieces are checked, and invalid puzzles have no solution.
+pub fn solve_with_options<B: Board>(
+    pieces: &[PlacedPiece],
+    options: &SolveOptions,
+) -> SolveOutcome {
+    match options.face_rule {
+        FaceRule::TenPiece => solve_with_options_impl::<B, TenPieceFacePolicy>(pieces, options),
+        FaceRule::NinePiece => solve_with_options_impl::<B, NinePieceFacePolicy>(pieces, options),
+        FaceRule::Unconstrained => solve_with_options_impl::<B, NoFacePolicy>(pieces, options),
+    }
+}
+
+fn solve_with_options_impl<B: Board, F: FacePolicy>(
+    pieces: &[PlacedPiece],
+    options: &SolveOptions,
+) -> SolveOutcome {
+    let root = match SearchNode::<B, F>::try_root(pieces, options.colors) {
+        Some(root) => root,
+        None => return SolveOutcome::NoSolution,
+    };
+    let mut counter = BudgetCounter::new(options);
+    match solve_rec(
+        root,
+        options.pruning,
+        &mut counter,
+        &mut placed_pieces(pieces),
+    ) {
+        Some(pieces_solution) => SolveOutcome::Solved(pieces_solution),
+        None if counter.should_abort() => SolveOutcome::Aborted {
+            nodes_explored: counter.get(),
+        },
+        None => SolveOutcome::NoSolution,
+    }
+}
+
+/// Check whether the board made of `pieces` is a dead end according to all the pruning rules,
+/// reporting the rule that fired to the `counter`. The pieces must fit on the board together, and
+/// are a dead end if they break the ten-piece rule.
+pub(crate) fn is_dead_end<B: Board, C: IterationCounter>(
+    pieces: &[PlacedPiece],
+    counter: &mut C,
+) -> bool {
+    let pruning = Pruning {
+        hole_patterns: true,
+        cell_count: true,
+        regions: true,
+    };
+    match SearchNode::<B, TenPieceFacePolicy>::root(pieces) {
+        Some(root) => root.is_dead_end(pruning, counter),
+        None => true,
+    }
+}
+
+/// Solve the puzzle, and return statistics about the search along with the solution.
+///
+/// Like `solve_with_options`, the given pieces are checked: invalid puzzles have no solution, and
+/// their search is empty.
+pub fn solve_with_stats<B: Board>(
+    pieces: &[PlacedPiece],
+) -> (Option<Vec<PlacedPiece>>, SearchStats) {
+    let mut stats = SearchStats::new();
+    let start = Instant::now();
+    let root = match SearchNode::<B, TenPieceFacePolicy>::try_root(pieces, ColorSet::full()) {
+        Some(root) => root,
+        None => return (None, stats),
+    };
+    let solution = solve_rec(
+        root,
+        Pruning::default(),
+        &mut stats,
+        &mut placed_pieces(pieces),
+    );
+    stats.elapsed = start.elapsed();
+    (solution, stats)
 }
 
 #[cfg(test)]
 pub fn solve_with_counter<B: Board>(pieces: &[PlacedPiece]) -> (Option<Vec<PlacedPiece>>, u64) {
-    let mut counter = SimpleIterationCounter(0);
-    let b = solve_impl::<B, SimpleIterationCounter, TenPieceFacePolicy>(pieces, &mut counter);
+    let options = SolveOptions::new().with_max_nodes(5000000);
+    let mut counter = BudgetCounter::new(&options);
+    let b = solve_impl::<B, BudgetCounter, TenPieceFacePolicy>(pieces, &mut counter);
+    assert!(!counter.should_abort(), "Too many iterations!");
     (b, counter.get())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::board::{BinaryBoard, DisplayBoard};
+    use crate::board::{BinaryBoard, BoardGeometry, DisplayBoard, FailurePattern, NUM_CELLS};
     use crate::puzzles::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::time::Duration;
 
     #[test]
     fn test_color_to_int() {
@@ -279,7 +1026,7 @@ mod tests {
                 .first_empty_cell(0),
             None
         );
-        assert_eq!(c, 471);
+        assert_eq!(c, 315);
     }
 
     #[test]
@@ -298,6 +1045,711 @@ mod tests {
                 .first_empty_cell(0),
             None
         );
-        assert_eq!(c, 746636);
+        assert_eq!(c, 463865);
+    }
+
+    #[test]
+    fn test_49_all_solutions() {
+        let solutions = solve_all::<BinaryBoard>(&*PIECES_49);
+        assert!(!solutions.is_empty());
+        for (i, pieces) in solutions.iter().enumerate() {
+            assert_eq!(pieces.len(), 10);
+            assert_eq!(&pieces[..3], &PIECES_49[..]);
+            assert_eq!(
+                DisplayBoard::from_placed_piece_list(pieces)
+                    .unwrap()
+                    .first_empty_cell(0),
+                None
+            );
+            assert!(!solutions[..i].contains(pieces));
+        }
+        let mut first = solve::<BinaryBoard>(&*PIECES_49).unwrap();
+        first.sort_by_key(|p| p.top_left);
+        let mut first_enumerated = solutions[0].clone();
+        first_enumerated.sort_by_key(|p| p.top_left);
+        assert_eq!(first, first_enumerated);
+        assert_eq!(solve_all::<DisplayBoard>(&*PIECES_49), solutions);
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let num_solutions = solve_all::<BinaryBoard>(&*PIECES_49).len() as u64;
+        assert_eq!(
+            count_solutions::<BinaryBoard>(&*PIECES_49, None),
+            num_solutions
+        );
+        assert_eq!(
+            count_solutions::<DisplayBoard>(&*PIECES_49, None),
+            num_solutions
+        );
+        assert_eq!(count_solutions::<BinaryBoard>(&*PIECES_49, Some(0)), 0);
+        assert_eq!(count_solutions::<BinaryBoard>(&*PIECES_49, Some(1)), 1);
+        assert_eq!(is_unique::<BinaryBoard>(&*PIECES_49), num_solutions == 1);
+        // Removing the hints gives more solutions.
+        assert_eq!(count_solutions::<BinaryBoard>(&PIECES_49[..1], Some(2)), 2);
+        assert!(!is_unique::<BinaryBoard>(&PIECES_49[..1]));
+    }
+
+    #[test]
+    fn test_parallel() {
+        for &num_threads in [1, 4].iter() {
+            let pieces = solve_parallel::<BinaryBoard>(&*PIECES_117, num_threads).unwrap();
+            assert_eq!(pieces.len(), 10);
+            assert_eq!(
+                DisplayBoard::from_placed_piece_list(&pieces)
+                    .unwrap()
+                    .first_empty_cell(0),
+                None
+            );
+            assert_eq!(
+                solve_all_parallel::<BinaryBoard>(&*PIECES_49, num_threads),
+                solve_all::<BinaryBoard>(&*PIECES_49)
+            );
+            assert_eq!(
+                count_solutions_parallel::<BinaryBoard>(&*PIECES_49, None, num_threads),
+                count_solutions::<BinaryBoard>(&*PIECES_49, None)
+            );
+            assert_eq!(
+                count_solutions_parallel::<DisplayBoard>(&PIECES_49[..1], Some(3), num_threads),
+                3
+            );
+        }
+    }
+
+    #[test]
+    fn test_parallel_with_options() {
+        let colors: Vec<Color> = COLOR_LIST
+            .iter()
+            .cloned()
+            .filter(|&c| c != Color::Purple)
+            .collect();
+        let options = SolveOptions::new().with_colors(&colors);
+        for &num_threads in [1, 4].iter() {
+            assert_eq!(
+                parallel::solve_parallel_with_options::<BinaryBoard>(&[], &options, num_threads),
+                SolveOutcome::NoSolution
+            );
+            let options = options.clone().with_face_rule(FaceRule::Unconstrained);
+            match parallel::solve_parallel_with_options::<BinaryBoard>(&[], &options, num_threads) {
+                SolveOutcome::Solved(pieces) => {
+                    assert_eq!(pieces.len(), 9);
+                    assert!(BinaryBoard::from_placed_piece_list(&pieces).is_some());
+                }
+                outcome => panic!("Unexpected outcome: {:?}", outcome),
+            }
+            assert_eq!(
+                parallel::count_solutions_parallel_with_policy::<BinaryBoard, NoFacePolicy>(
+                    &*PIECES_49,
+                    None,
+                    num_threads
+                ),
+                count_solutions_with_policy::<BinaryBoard, NoFacePolicy>(&*PIECES_49, None)
+            );
+            assert_eq!(
+                parallel::solve_all_parallel_with_policy::<BinaryBoard, NinePieceFacePolicy>(
+                    &*PIECES_49,
+                    num_threads
+                ),
+                Vec::<Vec<PlacedPiece>>::new()
+            );
+
+            let pruning = Pruning {
+                hole_patterns: false,
+                cell_count: false,
+                regions: true,
+            };
+            let options = SolveOptions::new().with_pruning(pruning);
+            match parallel::solve_parallel_with_options::<BinaryBoard>(
+                &*PIECES_117,
+                &options,
+                num_threads,
+            ) {
+                SolveOutcome::Solved(pieces) => assert_eq!(pieces.len(), 10),
+                outcome => panic!("Unexpected outcome: {:?}", outcome),
+            }
+            // The node limit is shared by the threads.
+            let options = SolveOptions::new().with_max_nodes(10);
+            assert_eq!(
+                parallel::solve_parallel_with_options::<BinaryBoard>(&[], &options, num_threads),
+                SolveOutcome::Aborted { nodes_explored: 10 }
+            );
+            let options = SolveOptions::new().with_cancel_token(Arc::new(AtomicBool::new(true)));
+            assert_eq!(
+                parallel::solve_parallel_with_options::<BinaryBoard>(
+                    &*PIECES_117,
+                    &options,
+                    num_threads
+                ),
+                SolveOutcome::Aborted { nodes_explored: 0 }
+            );
+        }
+    }
+
+    fn sorted_solutions(mut solutions: Vec<Vec<PlacedPiece>>) -> Vec<Vec<PlacedPiece>> {
+        for s in solutions.iter_mut() {
+            s.sort_by_key(|p| p.top_left);
+        }
+        solutions.sort_by_key(|s| s.iter().map(|p| p.piece.as_byte()).collect::<Vec<_>>());
+        solutions
+    }
+
+    #[test]
+    fn test_dlx() {
+        let solver = DlxSolver;
+        let pieces = solver.solve(&*PIECES_117).unwrap();
+        assert_eq!(
+            BinaryBoard::from_placed_piece_list(&pieces)
+                .unwrap()
+                .first_empty_cell(0),
+            None
+        );
+        let backtracking = BacktrackingSolver::<BinaryBoard>::default();
+        assert_eq!(
+            sorted_solutions(solver.solve_all(&*PIECES_49)),
+            sorted_solutions(backtracking.solve_all(&*PIECES_49))
+        );
+        assert_eq!(
+            solver.count_solutions(&PIECES_49[..1], Some(5)),
+            backtracking.count_solutions(&PIECES_49[..1], Some(5))
+        );
+        assert_eq!(
+            solver.count_solutions(&*PIECES_117, None),
+            backtracking.count_solutions(&*PIECES_117, None)
+        );
+
+        // Invalid puzzles have no solution.
+        let out_of_bounds = PlacedPiece {
+            top_left: 49,
+            ..PIECES_49[0]
+        };
+        assert_eq!(solver.solve(&[out_of_bounds]), None);
+        let overlap = PlacedPiece {
+            piece: PIECES_117[0].piece,
+            ..PIECES_49[0]
+        };
+        assert!(matches!(
+            BinaryBoard::try_from_placed_piece_list(&[PIECES_49[0], overlap]),
+            Err(PlacementError::Overlap { .. })
+        ));
+        assert_eq!(solver.count_solutions(&[PIECES_49[0], overlap], None), 0);
+        let duplicate = [PIECES_49[0], PIECES_49[0]];
+        assert!(solver.solve_all(&duplicate).is_empty());
+    }
+
+    /// Check that the solution covers each cell of the board exactly once, with distinct pieces.
+    fn assert_covers(solver: &GeometrySolver, solution: &[PlacedPiece]) {
+        let mut covered = vec![0; solver.geometry.num_cells()];
+        for p in solution {
+            for cell in solver.pieces.piece_cells(&solver.geometry, *p).unwrap() {
+                covered[cell as usize] += 1;
+            }
+        }
+        assert!(covered.iter().all(|&c| c == 1), "{:?}", solution);
+        let colors =
+            ColorSet::from_colors(&solution.iter().map(|p| p.piece.color()).collect::<Vec<_>>());
+        assert_eq!(colors.len() as usize, solution.len());
+    }
+
+    #[test]
+    fn test_geometry_solver() {
+        let standard =
+            GeometrySolver::new(BoardGeometry::STANDARD).with_face_rule(FaceRule::TenPiece);
+        let backtracking = BacktrackingSolver::<BinaryBoard>::default();
+        assert_eq!(
+            sorted_solutions(standard.solve_all(&*PIECES_49)),
+            sorted_solutions(backtracking.solve_all(&*PIECES_49))
+        );
+        assert_eq!(standard.count_solutions(&PIECES_49[..1], Some(5)), 5);
+
+        // A practice board, with fewer pieces.
+        let small = BoardGeometry::new(5, 4).unwrap();
+        let solver = GeometrySolver::new(small).with_colors(&COLOR_LIST[..6]);
+        let solutions = solver.solve_all(&[]);
+        assert!(!solutions.is_empty());
+        for solution in &solutions {
+            assert_covers(&solver, solution);
+        }
+        let given = &solutions[0][..1];
+        assert_eq!(
+            solver.count_solutions(given, None),
+            solutions.iter().filter(|s| s.contains(&given[0])).count() as u64
+        );
+
+        // A bigger board, with every piece. Half of them are given, since finding a solution
+        // from scratch takes a while.
+        let big = BoardGeometry::new(11, 5).unwrap();
+        let given: Vec<PlacedPiece> = [
+            (Color::Yellow, Face::A, Orientation::Up, 0),
+            (Color::Orange, Face::B, Orientation::Left, 34),
+            (Color::LightBlue, Face::B, Orientation::Down, 2),
+            (Color::LightGreen, Face::B, Orientation::Up, 3),
+            (Color::Green, Face::B, Orientation::Left, 5),
+        ]
+        .iter()
+        .map(|&(color, face, orientation, top_left)| PlacedPiece {
+            piece: Piece::new()
+                .with_color(color)
+                .with_face(face)
+                .with_orientation(orientation),
+            top_left,
+        })
+        .collect();
+        let solver = GeometrySolver::new(big);
+        let solution = solver.solve(&given).unwrap();
+        assert_eq!(solution.len(), 10);
+        assert_covers(&solver, &solution);
+        // Too big for the pieces.
+        let too_big = BoardGeometry::new(12, 5).unwrap();
+        assert_eq!(GeometrySolver::new(too_big).solve(&[]), None);
+
+        // Home-made pieces: straight lines of 4 balls, the same on both faces.
+        let lines = PieceSet::parse("a 4 A B\nb 4 A B\nc 4 A B\nd 4 A B").unwrap();
+        let solver = GeometrySolver::new(BoardGeometry::new(4, 4).unwrap()).with_pieces(lines);
+        // In rows or in columns, in any order, each one on any face and turned either way.
+        assert_eq!(solver.count_solutions(&[], None), 2 * 24 * 4u64.pow(4));
+        assert_covers(&solver, &solver.solve(&[]).unwrap());
+        let missing = PlacedPiece {
+            piece: solver.pieces.piece(4),
+            top_left: 0,
+        };
+        assert_eq!(solver.solve(&[missing]), None);
+
+        // A board of more than 64 cells, where the placements leaving a hole are pruned with a
+        // 128-bit board. Ten lines of 7 balls: the first seven are given, one on the right of
+        // each row, so the last three go upright on the left, in any order.
+        let text: Vec<String> = (0..10).map(|i| format!("l{} 7 A B", i)).collect();
+        let lines = PieceSet::parse(&text.join("\n")).unwrap();
+        let geometry = BoardGeometry::new(10, 7).unwrap();
+        let solver = GeometrySolver::new(geometry).with_pieces(lines);
+        let line = |index: u8, top_left: u8| PlacedPiece {
+            piece: solver
+                .pieces
+                .piece(index)
+                .with_orientation(Orientation::Right),
+            top_left,
+        };
+        let given: Vec<PlacedPiece> = (0..7).map(|row| line(row, row * 10 + 3)).collect();
+        for p in &given {
+            let cells = solver.pieces.piece_cells(&geometry, *p).unwrap();
+            assert_eq!(cells, (p.top_left..p.top_left + 7).collect::<Vec<_>>());
+        }
+        assert_eq!(solver.count_solutions(&given, None), 6 * 4u64.pow(3));
+        assert_covers(&solver, &solver.solve(&given).unwrap());
+        assert_covers(&solver, &solver.solve(&[]).unwrap());
+        // Two lines leaving a hole of one cell in the corner.
+        assert_eq!(solver.solve(&[line(0, 1), line(1, 10)]), None);
+    }
+
+    #[test]
+    fn test_piece_set_solver() {
+        let standard = PieceSet::standard();
+        let table = PlacementTable::new(&standard);
+        for top_left in 0..NUM_CELLS {
+            for &color in COLOR_LIST.iter() {
+                assert_eq!(
+                    table.placements(top_left, color),
+                    placements(top_left, color)
+                );
+            }
+        }
+        let solver =
+            PieceSetSolver::<BinaryBoard>::new(&standard).with_face_rule(FaceRule::TenPiece);
+        assert_eq!(
+            solver.count_solutions(&*PIECES_49, None),
+            count_solutions::<BinaryBoard>(&*PIECES_49, None)
+        );
+        assert_eq!(
+            solver.solve(&*PIECES_117),
+            solve::<BinaryBoard>(&*PIECES_117)
+        );
+
+        // Home-made pieces: four blocks of 2x5 balls, a block missing a corner, and a single
+        // ball to fill it.
+        let block = "5 A 0,1 1,1 2,1 3,1 4,1 B 0,1 1,1 2,1 3,1 4,1";
+        let text = format!(
+            "k0 {0}\nk1 {0}\nk2 {0}\nk3 {0}\nnotch 5 A 0,1 1,1 2,1 3,1 B 0,1 1,1 2,1 3,1\ndot 1 A B",
+            block
+        );
+        let set = PieceSet::parse(&text).unwrap();
+        let given: Vec<PlacedPiece> = (0..4)
+            .map(|i| PlacedPiece {
+                piece: set.piece(i),
+                top_left: 2 * i,
+            })
+            .collect();
+        // The notch stands on either end, on either face, and the ball fills its corner in any
+        // orientation. When the notch comes first, its corner is a single hole that the hole
+        // patterns would wrongly prune.
+        let expected = 2 * 2 * 8;
+        assert_eq!(
+            PieceSetSolver::<BinaryBoard>::new(&set).count_solutions(&given, None),
+            expected
+        );
+        assert_eq!(
+            PieceSetSolver::<DisplayBoard>::new(&set).count_solutions(&given, None),
+            expected
+        );
+        let geometry_solver = GeometrySolver::new(BoardGeometry::STANDARD).with_pieces(set.clone());
+        assert_eq!(geometry_solver.count_solutions(&given, None), expected);
+        let solver = PieceSetSolver::<DisplayBoard>::new(&set);
+        let solution = solver.solve(&given).unwrap();
+        assert_covers(&geometry_solver, &solution);
+        // The given pieces are placed with their shapes from the set.
+        assert_eq!(solver.solve(&given[..1]).unwrap()[..1], given[..1]);
+        let overlapping = [
+            given[0],
+            PlacedPiece {
+                top_left: 1,
+                ..given[1]
+            },
+        ];
+        assert_eq!(solver.solve(&overlapping), None);
+        assert_eq!(solver.count_solutions(&given, Some(3)), 3);
+
+        // Six lines of 10 balls for the five rows: one of them is left over.
+        let text: Vec<String> = (0..6).map(|i| format!("l{} 10 A B", i)).collect();
+        let lines = PieceSet::parse(&text.join("\n")).unwrap();
+        let geometry_solver =
+            GeometrySolver::new(BoardGeometry::STANDARD).with_pieces(lines.clone());
+        let solution = PieceSetSolver::<BinaryBoard>::new(&lines)
+            .solve(&[])
+            .unwrap();
+        assert_eq!(solution.len(), 5);
+        assert_covers(&geometry_solver, &solution);
+        // With three rows given, the last two take two of the three lines left, in any order,
+        // each one on any face and turned either way.
+        let given: Vec<PlacedPiece> = (0..3)
+            .map(|row| PlacedPiece {
+                piece: lines.piece(row).with_orientation(Orientation::Right),
+                top_left: row * 10,
+            })
+            .collect();
+        let expected = 3 * 2 * 4 * 4;
+        assert_eq!(geometry_solver.count_solutions(&given, None), expected);
+        assert_eq!(
+            PieceSetSolver::<BinaryBoard>::new(&lines).count_solutions(&given, None),
+            expected
+        );
+        assert_eq!(
+            PieceSetSolver::<DisplayBoard>::new(&lines).count_solutions(&given, None),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_solve_with_options() {
+        let solution = solve::<BinaryBoard>(&*PIECES_117).unwrap();
+        assert_eq!(
+            solve_with_options::<BinaryBoard>(&*PIECES_117, &SolveOptions::new()),
+            SolveOutcome::Solved(solution)
+        );
+        assert_eq!(
+            solve_with_options::<BinaryBoard>(
+                &*PIECES_117,
+                &SolveOptions::new().with_max_nodes(1000)
+            ),
+            SolveOutcome::Aborted {
+                nodes_explored: 1000
+            }
+        );
+        let cancelled = Arc::new(AtomicBool::new(true));
+        assert!(matches!(
+            solve_with_options::<DisplayBoard>(
+                &*PIECES_117,
+                &SolveOptions::new().with_cancel_token(cancelled)
+            ),
+            SolveOutcome::Aborted { .. }
+        ));
+        assert!(matches!(
+            solve_with_options::<BinaryBoard>(
+                &*PIECES_117,
+                &SolveOptions::new().with_timeout(Duration::from_secs(0))
+            ),
+            SolveOutcome::Aborted { .. }
+        ));
+        // Two pieces of the same color.
+        let duplicate = [PIECES_49[0], PIECES_49[0]];
+        assert_eq!(
+            solve_with_options::<BinaryBoard>(&duplicate, &SolveOptions::new()),
+            SolveOutcome::NoSolution
+        );
+    }
+
+    #[test]
+    fn test_solve_with_stats() {
+        let (pieces, stats) = solve_with_stats::<BinaryBoard>(&*PIECES_49);
+        assert_eq!(pieces, solve::<BinaryBoard>(&*PIECES_49));
+        assert_eq!(stats.placements_tried, 315);
+        // The root node has the 3 pieces from the puzzle.
+        assert_eq!(stats.nodes_per_depth[..4], [0, 0, 0, 1]);
+        assert_eq!(stats.nodes_per_depth[10], 1);
+        assert!(stats.pruned() > 0);
+        assert_eq!(
+            stats.pruned(),
+            FailurePattern::ALL
+                .iter()
+                .map(|&p| stats.pruned_by(p))
+                .sum::<u64>()
+        );
+        // Every placement tried either failed or created a new node.
+        assert_eq!(
+            stats.placements_tried,
+            stats.placement_failures + stats.nodes() - 1
+        );
+
+        // More pieces than the set has.
+        let mut crowded = pieces.unwrap();
+        crowded.push(crowded[0]);
+        let (pieces, stats) = solve_with_stats::<BinaryBoard>(&crowded);
+        assert_eq!(pieces, None);
+        assert_eq!(stats, SearchStats::new());
+
+        let mut merged = SearchStats::new();
+        merged.elapsed = Duration::from_secs(2);
+        let mut other = SearchStats::new();
+        other.elapsed = Duration::from_secs(1);
+        other.placements_tried = 3;
+        merged.merge(&other);
+        assert_eq!(merged.placements_tried, 3);
+        assert_eq!(merged.elapsed, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_fillable_sizes() {
+        assert_eq!(fillable_sizes(ColorSet(0), StandardShapes), 1);
+        let purple_only = ColorSet(1 << Color::Purple as u8);
+        assert_eq!(
+            fillable_sizes(purple_only, StandardShapes),
+            1 | 1 << 4 | 1 << 5
+        );
+        let sizes = fillable_sizes(ColorSet::full(), StandardShapes);
+        for size in [1, 2, 3, 7].iter() {
+            assert_eq!(sizes & 1 << size, 0);
+        }
+        for size in [4, 5, 6, 8, 46, 50, 56].iter() {
+            assert_ne!(sizes & 1 << size, 0);
+        }
+    }
+
+    #[test]
+    fn test_region_pruning() {
+        let all_rules = SolveOptions::new().with_pruning(Pruning {
+            regions: true,
+            ..Pruning::default()
+        });
+        let regions_only = SolveOptions::new().with_pruning(Pruning {
+            hole_patterns: false,
+            regions: true,
+            ..Pruning::default()
+        });
+        for puzzle in [&PIECES_49[..], &PIECES_117[..]].iter() {
+            let solution = SolveOutcome::Solved(solve::<BinaryBoard>(puzzle).unwrap());
+            assert_eq!(
+                solve_with_options::<BinaryBoard>(puzzle, &all_rules),
+                solution
+            );
+            assert_eq!(
+                solve_with_options::<BinaryBoard>(puzzle, &regions_only),
+                solution
+            );
+            assert_eq!(
+                solve_with_options::<DisplayBoard>(puzzle, &regions_only),
+                solution
+            );
+        }
+
+        let solve_stats = |pruning| {
+            let mut stats = SearchStats::new();
+            solve_rec(
+                SearchNode::<BinaryBoard, TenPieceFacePolicy>::root(&*PIECES_117).unwrap(),
+                pruning,
+                &mut stats,
+                &mut placed_pieces(&*PIECES_117),
+            );
+            stats
+        };
+        let default_stats = solve_stats(Pruning::default());
+        let region_stats = solve_stats(all_rules.pruning);
+        assert_eq!(default_stats.pruned_by_regions, 0);
+        assert!(region_stats.pruned_by_regions > 0);
+        assert!(region_stats.placements_tried < default_stats.placements_tried);
+    }
+
+    #[test]
+    fn test_face_b_range() {
+        let policy = TenPieceFacePolicy::from_placed_pieces(&*PIECES_49);
+        assert_eq!(policy.face_b_range(7), (3, 3));
+        assert_eq!(policy.face_b_range(2), (0, 2));
+        let policy = NinePieceFacePolicy::from_placed_pieces(&*PIECES_49);
+        assert_eq!(policy.face_b_range(6), (4, 6));
+    }
+
+    #[test]
+    fn test_cell_count_pruning() {
+        let root = SearchNode::<BinaryBoard, TenPieceFacePolicy>::root(&*PIECES_117).unwrap();
+        assert!(root.has_matching_cell_count());
+
+        // Without the purple piece, there aren't enough balls to fill the board: this is detected
+        // before trying any placement.
+        let colors: Vec<Color> = COLOR_LIST
+            .iter()
+            .cloned()
+            .filter(|&c| c != Color::Purple)
+            .collect();
+        let options = SolveOptions::new().with_colors(&colors).with_max_nodes(1);
+        let root =
+            SearchNode::<BinaryBoard, TenPieceFacePolicy>::try_root(&*PIECES_117, options.colors)
+                .unwrap();
+        assert!(!root.has_matching_cell_count());
+        assert_eq!(
+            solve_with_options::<BinaryBoard>(&*PIECES_117, &options),
+            SolveOutcome::NoSolution
+        );
+
+        // The given pieces must be among the available ones.
+        let options = SolveOptions::new().with_colors(&[Color::Yellow]);
+        assert_eq!(
+            solve_with_options::<BinaryBoard>(&*PIECES_117, &options),
+            SolveOutcome::NoSolution
+        );
+    }
+
+    #[test]
+    fn test_try_solve() {
+        assert_eq!(
+            try_solve::<BinaryBoard>(&*PIECES_49),
+            Ok(solve::<BinaryBoard>(&*PIECES_49))
+        );
+        assert_eq!(
+            try_count_solutions::<BinaryBoard>(&*PIECES_117, Some(2)),
+            Ok(1)
+        );
+        let mut pieces = PIECES_49.to_vec();
+        pieces.push(pieces[0]);
+        assert_eq!(
+            try_solve::<BinaryBoard>(&pieces),
+            Err(PlacementError::DuplicateColor(Color::Yellow))
+        );
+        // Seven pieces on face A can't be part of a solution, even if they fit.
+        let mut faces_a = Vec::new();
+        let mut filled = 0;
+        for &color in &COLOR_LIST[..7] {
+            let (top_left, placement) = (0..50)
+                .flat_map(|top_left| {
+                    placements(top_left, color)
+                        .iter()
+                        .map(move |p| (top_left, p))
+                })
+                .find(|(_, p)| p.piece().face() == Face::A && p.mask & filled == 0)
+                .unwrap();
+            filled |= placement.mask;
+            faces_a.push(PlacedPiece {
+                piece: placement.piece(),
+                top_left,
+            });
+        }
+        assert_eq!(
+            try_count_solutions::<DisplayBoard>(&faces_a, None),
+            Err(PlacementError::FaceQuotaExceeded(Face::A))
+        );
+        assert_eq!(
+            solve_with_options::<BinaryBoard>(&faces_a, &SolveOptions::new()),
+            SolveOutcome::NoSolution
+        );
+        // The unchecked functions don't find any solution either.
+        assert_eq!(count_solutions::<BinaryBoard>(&faces_a, None), 0);
+        assert_eq!(solve::<DisplayBoard>(&faces_a), None);
+        assert_eq!(
+            count_solutions_with_policy::<BinaryBoard, NinePieceFacePolicy>(&faces_a, None),
+            0
+        );
+        assert_eq!(
+            parallel::count_solutions_parallel::<BinaryBoard>(&faces_a, None, 2),
+            0
+        );
+        assert_eq!(DlxSolver.count_solutions(&faces_a, None), 0);
+        let policy = TenPieceFacePolicy::from_placed_pieces(&faces_a);
+        assert_eq!(policy.face_b_range(3), (3, 3));
+    }
+
+    #[test]
+    fn test_cell_count_with_pieces_left_over() {
+        // The ten pieces can't fill the board without all of them.
+        let root = SearchNode::<BinaryBoard, TenPieceFacePolicy>::root(&[]).unwrap();
+        assert!(root.all_pieces_needed);
+
+        let colors: Vec<Color> = COLOR_LIST
+            .iter()
+            .cloned()
+            .filter(|&c| c != Color::Purple)
+            .collect();
+        let options = SolveOptions::new()
+            .with_colors(&colors)
+            .with_face_rule(FaceRule::Unconstrained);
+        let nine_pieces = match solve_with_options::<BinaryBoard>(&[], &options) {
+            SolveOutcome::Solved(pieces) => pieces,
+            outcome => panic!("Unexpected outcome: {:?}", outcome),
+        };
+        // With all the colors, the purple piece is left over.
+        let givens = &nine_pieces[..7];
+        let root = SearchNode::<BinaryBoard, NoFacePolicy>::try_root(givens, ColorSet::full());
+        assert!(!root.unwrap().all_pieces_needed);
+        let options = SolveOptions::new().with_face_rule(FaceRule::Unconstrained);
+        for &cell_count in [true, false].iter() {
+            let pruning = Pruning {
+                cell_count,
+                ..Pruning::default()
+            };
+            match solve_with_options::<BinaryBoard>(givens, &options.clone().with_pruning(pruning))
+            {
+                SolveOutcome::Solved(pieces) => {
+                    assert_eq!(&pieces[..7], givens);
+                    assert!(BinaryBoard::from_placed_piece_list(&pieces).is_some());
+                }
+                outcome => panic!("Unexpected outcome: {:?}", outcome),
+            }
+        }
+    }
+
+    #[test]
+    fn test_face_rule() {
+        // Without the purple piece, the board can only be filled with 8 pieces on face B.
+        let colors: Vec<Color> = COLOR_LIST
+            .iter()
+            .cloned()
+            .filter(|&c| c != Color::Purple)
+            .collect();
+        let options = SolveOptions::new().with_colors(&colors);
+        assert_eq!(
+            solve_with_options::<BinaryBoard>(&[], &options),
+            SolveOutcome::NoSolution
+        );
+        let options = options.with_face_rule(FaceRule::Unconstrained);
+        let pieces = match solve_with_options::<BinaryBoard>(&[], &options) {
+            SolveOutcome::Solved(pieces) => pieces,
+            outcome => panic!("Unexpected outcome: {:?}", outcome),
+        };
+        assert_eq!(pieces.len(), 9);
+        assert_eq!(
+            pieces.iter().filter(|p| p.piece.face() == Face::B).count(),
+            8
+        );
+        assert!(BinaryBoard::from_placed_piece_list(&pieces).is_some());
+        // The nine-piece rule only limits the number of pieces on face A.
+        let options = options.with_face_rule(FaceRule::NinePiece);
+        assert_eq!(
+            solve_with_options::<BinaryBoard>(&[], &options),
+            SolveOutcome::Solved(pieces)
+        );
+
+        // With all the pieces, the number of balls forces the ten-piece distribution anyway.
+        assert_eq!(
+            count_solutions_with_policy::<BinaryBoard, NoFacePolicy>(&*PIECES_49, None),
+            count_solutions::<BinaryBoard>(&*PIECES_49, None)
+        );
+        assert_eq!(
+            solve_with_policy::<BinaryBoard, NoFacePolicy>(&*PIECES_117),
+            solve::<BinaryBoard>(&*PIECES_117)
+        );
     }
 }
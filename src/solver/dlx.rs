@@ -0,0 +1,553 @@
+//! Exact cover solver using Knuth's Dancing Links (Algorithm X).
+//!
+//! Each empty cell and each color left is a column, and each legal placement of a remaining
+//! piece is a row covering its cells and its color. Instead of always filling the top-left
+//! cell, the search branches on the column with the fewest rows left.
+//!
+//! Since it doesn't rely on the precomputed tables of the standard board, it also solves
+//! boards of other sizes, with other pieces, see `GeometrySolver`.
+
+use super::*;
+use crate::board::wide_binary_board::has_common_failure;
+use crate::board::{placement_mask, placements, BinaryBoard, BoardGeometry, NUM_CELLS};
+
+/// Index of the root header in the node arrays.
+const ROOT: usize = 0;
+
+/// The sparse exact cover matrix, as circular doubly linked lists of nodes.
+///
+/// Node 0 is the root, nodes 1 to `num_columns` are the column headers, and the rest are the
+/// ones of the matrix.
+struct Matrix {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    /// The column header of each node.
+    column: Vec<usize>,
+    /// The row of each node, as an index in `rows`.
+    row: Vec<usize>,
+    /// The number of nodes in each column, indexed by header.
+    size: Vec<usize>,
+    /// The piece placement for each row.
+    rows: Vec<PlacedPiece>,
+    /// Prune the placements leaving a hole no piece can fill, if the board is small enough.
+    holes: Option<HoleCheck>,
+}
+
+/// The full cells of the board as a bitfield, to look for the failure patterns of
+/// `Board::check_common_failures` after each placement.
+///
+/// This only holds while the smallest piece left has more than 3 balls, since the patterns are
+/// holes of up to 3 cells.
+struct HoleCheck {
+    width: u8,
+    /// The full cells, with the bits past the end of the board set.
+    filled: u128,
+    /// The cells covered by each row.
+    row_cells: Vec<u128>,
+}
+
+impl HoleCheck {
+    /// Whether placing `row` leaves a hole.
+    fn leaves_hole(&self, row: usize) -> bool {
+        has_common_failure(self.filled | self.row_cells[row], self.width)
+    }
+}
+
+impl Matrix {
+    /// Build the matrix for the board left after placing `pieces`.
+    ///
+    /// Returns None if the pieces can't start a puzzle, see `check_pieces`.
+    fn new(pieces: &[PlacedPiece]) -> Option<Self> {
+        check_pieces::<BinaryBoard>(pieces).ok()?;
+        let mut filled = 0u64;
+        let mut colors_left = ColorSet::full();
+        for p in pieces {
+            filled |= placement_mask(*p)?;
+            colors_left.remove(p.piece.color());
+        }
+        // Map each empty cell and each color left to a column header.
+        let mut cell_column = [0usize; NUM_CELLS as usize];
+        let mut color_column = [0usize; 10];
+        let mut num_columns = 0;
+        for (cell, column) in cell_column.iter_mut().enumerate() {
+            if filled & (1 << cell) == 0 {
+                num_columns += 1;
+                *column = num_columns;
+            }
+        }
+        for &c in COLOR_LIST.iter() {
+            if colors_left.contains(c) {
+                num_columns += 1;
+                color_column[c as usize] = num_columns;
+            }
+        }
+
+        let mut matrix = Matrix::with_columns(num_columns, num_columns);
+        for top_left in 0..NUM_CELLS {
+            for &c in COLOR_LIST.iter() {
+                if !colors_left.contains(c) {
+                    continue;
+                }
+                for placement in placements(top_left, c) {
+                    if placement.mask & filled != 0 {
+                        continue;
+                    }
+                    let mut columns: Vec<usize> = (0..NUM_CELLS as usize)
+                        .filter(|cell| placement.mask & (1 << cell) != 0)
+                        .map(|cell| cell_column[cell])
+                        .collect();
+                    columns.push(color_column[c as usize]);
+                    let piece = PlacedPiece {
+                        piece: placement.piece(),
+                        top_left,
+                    };
+                    matrix.add_row(piece, &columns);
+                }
+            }
+        }
+        Some(matrix)
+    }
+
+    /// Build the matrix for a board of any size, with the given pieces placed and the `colors`
+    /// of `piece_set` available. Unlike on the standard board, the pieces left don't all have to
+    /// be used.
+    ///
+    /// Returns None if the given pieces don't fit on the board together, or if the pieces left
+    /// are too small to cover the empty cells: the search would take very long to find it out.
+    fn with_geometry(
+        geometry: &BoardGeometry,
+        piece_set: &PieceSet,
+        pieces: &[PlacedPiece],
+        colors: ColorSet,
+    ) -> Option<Self> {
+        let num_cells = geometry.num_cells();
+        let mut filled = vec![false; num_cells];
+        let mut colors_left = ColorSet(colors.0 & piece_set.colors().0);
+        for p in pieces {
+            if !colors_left.remove(p.piece.color()) {
+                return None;
+            }
+            for cell in piece_set.piece_cells(geometry, *p)? {
+                if std::mem::replace(&mut filled[cell as usize], true) {
+                    return None;
+                }
+            }
+        }
+        let max_cells: usize = COLOR_LIST
+            .iter()
+            .filter(|&&c| colors_left.contains(c))
+            .map(|&c| {
+                let piece = Piece::new().with_color(c);
+                let size_a = piece_set.num_balls(piece.with_face(Face::A));
+                size_a.max(piece_set.num_balls(piece.with_face(Face::B)))
+            })
+            .sum();
+        if max_cells < filled.iter().filter(|&&f| !f).count() {
+            return None;
+        }
+        let min_cells = COLOR_LIST
+            .iter()
+            .filter(|&&c| colors_left.contains(c))
+            .map(|&c| {
+                let piece = Piece::new().with_color(c);
+                let size_a = piece_set.num_balls(piece.with_face(Face::A));
+                size_a.min(piece_set.num_balls(piece.with_face(Face::B)))
+            })
+            .min()
+            .unwrap_or(0);
+        let mut holes = if num_cells <= 128 && min_cells > 3 {
+            let filled = (0..num_cells)
+                .filter(|&cell| filled[cell])
+                .fold(!0u128 << (num_cells - 1) << 1, |acc, cell| acc | 1 << cell);
+            if has_common_failure(filled, geometry.width()) {
+                return None;
+            }
+            Some(HoleCheck {
+                width: geometry.width(),
+                filled,
+                row_cells: Vec::new(),
+            })
+        } else {
+            None
+        };
+        // The empty cells must be covered, so they are the primary columns. The colors are
+        // secondary columns: each piece is used at most once.
+        let mut cell_column = vec![0usize; num_cells];
+        let mut color_column = [0usize; 10];
+        let mut num_columns = 0;
+        for (cell, column) in cell_column.iter_mut().enumerate() {
+            if !filled[cell] {
+                num_columns += 1;
+                *column = num_columns;
+            }
+        }
+        let num_primary = num_columns;
+        for &c in COLOR_LIST.iter() {
+            if colors_left.contains(c) {
+                num_columns += 1;
+                color_column[c as usize] = num_columns;
+            }
+        }
+
+        let mut matrix = Matrix::with_columns(num_primary, num_columns);
+        for top_left in 0..num_cells {
+            for &c in COLOR_LIST.iter() {
+                if !colors_left.contains(c) {
+                    continue;
+                }
+                for byte in c as u8 * 8..c as u8 * 8 + 8 {
+                    let piece = PlacedPiece {
+                        piece: Piece::from_byte(byte),
+                        top_left: top_left as u8,
+                    };
+                    let cells = match piece_set.piece_cells(geometry, piece) {
+                        Some(cells) => cells,
+                        None => continue,
+                    };
+                    if cells.iter().any(|&cell| filled[cell as usize]) {
+                        continue;
+                    }
+                    let mut columns: Vec<usize> = cells
+                        .iter()
+                        .map(|&cell| cell_column[cell as usize])
+                        .collect();
+                    columns.push(color_column[c as usize]);
+                    matrix.add_row(piece, &columns);
+                    if let Some(holes) = &mut holes {
+                        let row_cells = cells.iter().fold(0, |acc, &cell| acc | 1 << cell);
+                        holes.row_cells.push(row_cells);
+                    }
+                }
+            }
+        }
+        matrix.holes = holes;
+        Some(matrix)
+    }
+
+    /// A matrix without rows, with the headers of `num_columns` columns. Only the first
+    /// `num_primary` ones are linked to the root, the others are optional.
+    fn with_columns(num_primary: usize, num_columns: usize) -> Self {
+        let mut matrix = Matrix {
+            left: Vec::new(),
+            right: Vec::new(),
+            up: Vec::new(),
+            down: Vec::new(),
+            column: Vec::new(),
+            row: Vec::new(),
+            size: vec![0; num_columns + 1],
+            rows: Vec::new(),
+            holes: None,
+        };
+        for node in 0..=num_columns {
+            if node > num_primary {
+                matrix.left.push(node);
+                matrix.right.push(node);
+            } else {
+                matrix
+                    .left
+                    .push(if node == 0 { num_primary } else { node - 1 });
+                matrix
+                    .right
+                    .push(if node == num_primary { 0 } else { node + 1 });
+            }
+            matrix.up.push(node);
+            matrix.down.push(node);
+            matrix.column.push(node);
+            matrix.row.push(usize::MAX);
+        }
+        matrix
+    }
+
+    fn add_row(&mut self, piece: PlacedPiece, columns: &[usize]) {
+        let row = self.rows.len();
+        self.rows.push(piece);
+        let first = self.left.len();
+        for (i, &header) in columns.iter().enumerate() {
+            let node = first + i;
+            self.left.push(if i == 0 {
+                first + columns.len() - 1
+            } else {
+                node - 1
+            });
+            self.right.push(if i == columns.len() - 1 {
+                first
+            } else {
+                node + 1
+            });
+            self.up.push(self.up[header]);
+            self.down.push(header);
+            self.column.push(header);
+            self.row.push(row);
+            let last = self.up[header];
+            self.down[last] = node;
+            self.up[header] = node;
+            self.size[header] += 1;
+        }
+    }
+
+    fn cover(&mut self, header: usize) {
+        self.right[self.left[header]] = self.right[header];
+        self.left[self.right[header]] = self.left[header];
+        let mut i = self.down[header];
+        while i != header {
+            let mut j = self.right[i];
+            while j != i {
+                self.down[self.up[j]] = self.down[j];
+                self.up[self.down[j]] = self.up[j];
+                self.size[self.column[j]] -= 1;
+                j = self.right[j];
+            }
+            i = self.down[i];
+        }
+    }
+
+    fn uncover(&mut self, header: usize) {
+        let mut i = self.up[header];
+        while i != header {
+            let mut j = self.left[i];
+            while j != i {
+                self.size[self.column[j]] += 1;
+                self.down[self.up[j]] = j;
+                self.up[self.down[j]] = j;
+                j = self.left[j];
+            }
+            i = self.up[i];
+        }
+        self.right[self.left[header]] = header;
+        self.left[self.right[header]] = header;
+    }
+
+    /// The column with the fewest rows left.
+    fn choose_column(&self) -> usize {
+        let mut best = self.right[ROOT];
+        let mut header = self.right[best];
+        while header != ROOT {
+            if self.size[header] < self.size[best] {
+                best = header;
+            }
+            header = self.right[header];
+        }
+        best
+    }
+
+    /// Explore every exact cover, calling `visitor` on each one with the chosen rows.
+    /// Returns false if the visitor asked to stop the search.
+    fn search<F: FacePolicy, V>(
+        &mut self,
+        face_policy: F,
+        placed: &mut Vec<PlacedPiece>,
+        visitor: &mut V,
+    ) -> bool
+    where
+        V: FnMut(&[PlacedPiece]) -> bool,
+    {
+        if self.right[ROOT] == ROOT {
+            return visitor(placed);
+        }
+        let header = self.choose_column();
+        if self.size[header] == 0 {
+            return true;
+        }
+        self.cover(header);
+        let mut keep_going = true;
+        let mut node = self.down[header];
+        while node != header && keep_going {
+            let row = self.row[node];
+            let piece = self.rows[row];
+            let face = piece.piece.face();
+            let leaves_hole = self.holes.as_ref().is_some_and(|h| h.leaves_hole(row));
+            if face_policy.can_add_face(face) && !leaves_hole {
+                let mut j = self.right[node];
+                while j != node {
+                    self.cover(self.column[j]);
+                    j = self.right[j];
+                }
+                if let Some(holes) = &mut self.holes {
+                    holes.filled |= holes.row_cells[row];
+                }
+                placed.push(piece);
+                keep_going = self.search(face_policy.with_face(face), placed, visitor);
+                placed.pop();
+                if let Some(holes) = &mut self.holes {
+                    holes.filled &= !holes.row_cells[row];
+                }
+                let mut j = self.left[node];
+                while j != node {
+                    self.uncover(self.column[j]);
+                    j = self.left[j];
+                }
+            }
+            node = self.down[node];
+        }
+        self.uncover(header);
+        keep_going
+    }
+}
+
+/// Call `visitor` on every solution of the puzzle, found with Dancing Links.
+/// Like `for_each_solution`, each solution starts with the given `pieces`. Invalid puzzles, see
+/// `check_pieces`, have no solution.
+pub fn dlx_for_each_solution<V>(pieces: &[PlacedPiece], mut visitor: V)
+where
+    V: FnMut(&[PlacedPiece]) -> bool,
+{
+    let mut matrix = match Matrix::new(pieces) {
+        Some(matrix) => matrix,
+        None => return,
+    };
+    let mut placed = Vec::with_capacity(10);
+    placed.extend_from_slice(pieces);
+    matrix.search(
+        TenPieceFacePolicy::from_placed_pieces(pieces),
+        &mut placed,
+        &mut visitor,
+    );
+}
+
+/// Solver backend using Dancing Links, see the module documentation.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DlxSolver;
+
+impl Solver for DlxSolver {
+    fn solve(&self, pieces: &[PlacedPiece]) -> Option<Vec<PlacedPiece>> {
+        let mut solution = None;
+        dlx_for_each_solution(pieces, |s| {
+            solution = Some(s.to_vec());
+            false
+        });
+        solution
+    }
+
+    fn solve_all(&self, pieces: &[PlacedPiece]) -> Vec<Vec<PlacedPiece>> {
+        let mut solutions = Vec::new();
+        dlx_for_each_solution(pieces, |s| {
+            solutions.push(s.to_vec());
+            true
+        });
+        solutions
+    }
+
+    fn count_solutions(&self, pieces: &[PlacedPiece], limit: Option<u64>) -> u64 {
+        let limit = limit.unwrap_or(u64::MAX);
+        let mut count = 0;
+        if limit > 0 {
+            dlx_for_each_solution(pieces, |_| {
+                count += 1;
+                count < limit
+            });
+        }
+        count
+    }
+}
+
+/// Solver backend for boards of any size, using Dancing Links.
+///
+/// A solution covers every cell of the board, with each piece of `colors` used at most once.
+/// The faces follow `face_rule`, which is unconstrained by default since the ten-piece rule
+/// only makes sense on the standard board.
+///
+/// The pieces have the shapes of `pieces`, the standard ones by default. Only the colors in
+/// both `colors` and `pieces` are used.
+///
+/// On boards of up to 128 cells, the placements leaving a hole too small for every piece left
+/// are skipped, with the failure patterns of `WideBinaryBoard`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeometrySolver {
+    pub geometry: BoardGeometry,
+    pub pieces: PieceSet,
+    pub colors: ColorSet,
+    pub face_rule: FaceRule,
+}
+
+impl GeometrySolver {
+    pub fn new(geometry: BoardGeometry) -> Self {
+        GeometrySolver {
+            geometry,
+            pieces: PieceSet::standard(),
+            colors: ColorSet::full(),
+            face_rule: FaceRule::Unconstrained,
+        }
+    }
+
+    /// Use the pieces of this set instead of the standard ones.
+    pub fn with_pieces(mut self, pieces: PieceSet) -> Self {
+        self.pieces = pieces;
+        self
+    }
+
+    /// Only use the pieces of these colors.
+    pub fn with_colors(mut self, colors: &[Color]) -> Self {
+        self.colors = ColorSet::from_colors(colors);
+        self
+    }
+
+    pub fn with_face_rule(mut self, face_rule: FaceRule) -> Self {
+        self.face_rule = face_rule;
+        self
+    }
+
+    /// Call `visitor` on every solution of the puzzle, until it returns false. Each solution
+    /// starts with the given `pieces`, whose cells are numbered on this board.
+    pub fn for_each_solution<V>(&self, pieces: &[PlacedPiece], mut visitor: V)
+    where
+        V: FnMut(&[PlacedPiece]) -> bool,
+    {
+        let mut matrix =
+            match Matrix::with_geometry(&self.geometry, &self.pieces, pieces, self.colors) {
+                Some(matrix) => matrix,
+                None => return,
+            };
+        let mut placed = pieces.to_vec();
+        match self.face_rule {
+            FaceRule::TenPiece => {
+                let policy = TenPieceFacePolicy::from_placed_pieces(pieces);
+                if policy.exceeded_face().is_none() {
+                    matrix.search(policy, &mut placed, &mut visitor);
+                }
+            }
+            FaceRule::NinePiece => {
+                let policy = NinePieceFacePolicy::from_placed_pieces(pieces);
+                if policy.exceeded_face().is_none() {
+                    matrix.search(policy, &mut placed, &mut visitor);
+                }
+            }
+            FaceRule::Unconstrained => {
+                matrix.search(NoFacePolicy, &mut placed, &mut visitor);
+            }
+        }
+    }
+}
+
+impl Solver for GeometrySolver {
+    fn solve(&self, pieces: &[PlacedPiece]) -> Option<Vec<PlacedPiece>> {
+        let mut solution = None;
+        self.for_each_solution(pieces, |s| {
+            solution = Some(s.to_vec());
+            false
+        });
+        solution
+    }
+
+    fn solve_all(&self, pieces: &[PlacedPiece]) -> Vec<Vec<PlacedPiece>> {
+        let mut solutions = Vec::new();
+        self.for_each_solution(pieces, |s| {
+            solutions.push(s.to_vec());
+            true
+        });
+        solutions
+    }
+
+    fn count_solutions(&self, pieces: &[PlacedPiece], limit: Option<u64>) -> u64 {
+        let limit = limit.unwrap_or(u64::MAX);
+        let mut count = 0;
+        if limit > 0 {
+            self.for_each_solution(pieces, |_| {
+                count += 1;
+                count < limit
+            });
+        }
+        count
+    }
+}
@@ -0,0 +1,181 @@
+//! Limits on the search, to run the solver on arbitrary puzzles without hanging.
+
+use super::{ColorSet, IterationCounter};
+use crate::pieces::{Color, PlacedPiece};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How often to check the clock and the cancellation token, in nodes.
+/// Must be a power of 2.
+pub(crate) const CHECK_INTERVAL: u64 = 4096;
+
+/// Which rules to use to cut dead branches of the search.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pruning {
+    /// Look for the small holes of `Board::check_common_failures`.
+    pub hole_patterns: bool,
+    /// Check that the pieces left can cover the number of empty cells: exactly when all of them
+    /// are needed to fill the board, as with the ten pieces, and at least otherwise.
+    pub cell_count: bool,
+    /// Check that each connected region of empty cells can be filled exactly by the pieces left.
+    /// This is more thorough than the hole patterns, but slower.
+    pub regions: bool,
+}
+
+impl Default for Pruning {
+    fn default() -> Self {
+        Pruning {
+            hole_patterns: true,
+            cell_count: true,
+            regions: false,
+        }
+    }
+}
+
+/// Which `FacePolicy` to use for the search.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum FaceRule {
+    /// `TenPieceFacePolicy`: 6 pieces on face A, 4 on face B.
+    #[default]
+    TenPiece,
+    /// `NinePieceFacePolicy`: at most 4 pieces on face A.
+    NinePiece,
+    /// `NoFacePolicy`: any number of pieces on each face.
+    Unconstrained,
+}
+
+/// Configuration of a search. By default, it has no limits.
+#[derive(Debug, Default, Clone)]
+pub struct SolveOptions {
+    /// The maximum number of nodes to explore.
+    pub max_nodes: Option<u64>,
+    /// The time after which to give up.
+    pub deadline: Option<Instant>,
+    /// Setting this to true from another thread stops the search.
+    pub cancel_token: Option<Arc<AtomicBool>>,
+    /// The pruning rules to use.
+    pub pruning: Pruning,
+    /// The pieces available to solve the puzzle, including the ones given. By default, all of
+    /// them.
+    pub colors: ColorSet,
+    /// The rule on the faces of the pieces. By default, the one of the ten-piece puzzles.
+    pub face_rule: FaceRule,
+}
+
+impl SolveOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_nodes(mut self, max_nodes: u64) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Set the deadline to `timeout` from now.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn with_cancel_token(mut self, cancel_token: Arc<AtomicBool>) -> Self {
+        self.cancel_token = Some(cancel_token);
+        self
+    }
+
+    pub fn with_pruning(mut self, pruning: Pruning) -> Self {
+        self.pruning = pruning;
+        self
+    }
+
+    pub fn with_colors(mut self, colors: &[Color]) -> Self {
+        self.colors = ColorSet::from_colors(colors);
+        self
+    }
+
+    pub fn with_face_rule(mut self, face_rule: FaceRule) -> Self {
+        self.face_rule = face_rule;
+        self
+    }
+}
+
+/// The result of a search with `SolveOptions`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum SolveOutcome {
+    /// The puzzle was solved, with all the pieces on the board.
+    Solved(Vec<PlacedPiece>),
+    /// The whole search tree was explored without finding a solution.
+    NoSolution,
+    /// The search was stopped by one of the limits before it could finish.
+    Aborted { nodes_explored: u64 },
+}
+
+/// Counts the nodes, and requests an abort once one of the limits of the options is hit.
+pub(crate) struct BudgetCounter<'a> {
+    nodes: u64,
+    aborted: bool,
+    options: &'a SolveOptions,
+}
+
+impl<'a> BudgetCounter<'a> {
+    pub fn new(options: &'a SolveOptions) -> Self {
+        let mut counter = BudgetCounter {
+            nodes: 0,
+            aborted: false,
+            options,
+        };
+        counter.check_slow_limits();
+        counter
+    }
+
+    /// Check the limits that are too expensive to check at every node.
+    fn check_slow_limits(&mut self) {
+        if let Some(deadline) = self.options.deadline {
+            self.aborted |= Instant::now() >= deadline;
+        }
+        if let Some(cancel_token) = &self.options.cancel_token {
+            self.aborted |= cancel_token.load(Ordering::Relaxed);
+        }
+    }
+}
+
+impl IterationCounter for BudgetCounter<'_> {
+    #[inline]
+    fn increment(&mut self) {
+        if let Some(max_nodes) = self.options.max_nodes {
+            if self.nodes >= max_nodes {
+                // Don't count the node, it won't be explored.
+                self.aborted = true;
+                return;
+            }
+        }
+        self.nodes += 1;
+        if self.nodes & (CHECK_INTERVAL - 1) == 0 {
+            self.check_slow_limits();
+        }
+    }
+    #[inline]
+    fn get(&self) -> u64 {
+        self.nodes
+    }
+    #[inline]
+    fn should_abort(&self) -> bool {
+        self.aborted
+    }
+}
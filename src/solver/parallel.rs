@@ -0,0 +1,469 @@
+//! Multithreaded solver, splitting the top levels of the search tree across worker threads.
+
+use super::options::CHECK_INTERVAL;
+use super::*;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Never expand the search tree deeper than this to create tasks.
+const MAX_SPLIT_DEPTH: u8 = 3;
+/// How many tasks to aim for per thread, so that idle threads have something to steal.
+const TASKS_PER_THREAD: usize = 32;
+
+/// A subtree of the search, rooted at a partially filled board.
+struct Task<B: Board, F: FacePolicy> {
+    node: SearchNode<B, F>,
+    /// All the pieces on the board, including the ones from the puzzle.
+    placed: Vec<PlacedPiece>,
+}
+
+impl<B: Board, F: FacePolicy> Task<B, F> {
+    fn root(node: SearchNode<B, F>, pieces: &[PlacedPiece]) -> Self {
+        Task {
+            node,
+            placed: pieces.to_vec(),
+        }
+    }
+
+    /// Push the children of this task to `out`, in search order.
+    /// A task with a full board is its own child, failed tasks have no children.
+    fn expand(self, pruning: Pruning, out: &mut Vec<Self>) {
+        let node = self.node;
+        if node.is_dead_end(pruning, &mut NoOpIterationCounter {}) {
+            return;
+        }
+        let index = match node.board.first_empty_cell(node.empty_index_lower_bound) {
+            Some(index) => index,
+            None => {
+                out.push(self);
+                return;
+            }
+        };
+        for &c in COLOR_LIST.iter() {
+            if !node.colors_left.contains(c) {
+                continue;
+            }
+            for placement in placements(index, c) {
+                let piece = placement.piece();
+                let face = piece.face();
+                if !node.face_policy.can_add_face(face) {
+                    continue;
+                }
+                if let Some(board) = node.board.maybe_with_placement(placement, index) {
+                    let mut placed = self.placed.clone();
+                    placed.push(PlacedPiece {
+                        piece,
+                        top_left: index,
+                    });
+                    out.push(Task {
+                        node: SearchNode {
+                            board,
+                            colors_left: node.colors_left.without_color(c),
+                            empty_index_lower_bound: index + 1,
+                            face_policy: node.face_policy.with_face(face),
+                            shapes: node.shapes,
+                            all_pieces_needed: node.all_pieces_needed,
+                        },
+                        placed,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Split the search from `root` into enough tasks to keep `num_threads` busy, numbered in search
+/// order.
+fn split<B: Board, F: FacePolicy>(
+    root: Option<Task<B, F>>,
+    pruning: Pruning,
+    num_threads: usize,
+) -> Vec<Task<B, F>> {
+    let mut tasks: Vec<_> = root.into_iter().collect();
+    for _ in 0..MAX_SPLIT_DEPTH {
+        if tasks.len() >= num_threads * TASKS_PER_THREAD {
+            break;
+        }
+        let mut children = Vec::with_capacity(tasks.len() * 8);
+        for task in tasks {
+            task.expand(pruning, &mut children);
+        }
+        tasks = children;
+    }
+    tasks
+}
+
+/// One task queue per thread. Threads take work from the front of their own queue, and steal
+/// from the back of the other queues when theirs is empty.
+struct WorkQueues<T> {
+    queues: Vec<Mutex<VecDeque<T>>>,
+}
+
+impl<T> WorkQueues<T> {
+    fn new(tasks: Vec<T>, num_threads: usize) -> Self {
+        let mut queues: Vec<VecDeque<T>> = (0..num_threads).map(|_| VecDeque::new()).collect();
+        // Deal the tasks in contiguous chunks, so each thread starts on its own subtree.
+        let chunk_size = tasks.len().div_ceil(num_threads);
+        for (i, task) in tasks.into_iter().enumerate() {
+            queues[i / chunk_size.max(1)].push_back(task);
+        }
+        WorkQueues {
+            queues: queues.into_iter().map(Mutex::new).collect(),
+        }
+    }
+
+    fn next(&self, worker: usize) -> Option<T> {
+        if let Some(task) = self.queues[worker].lock().unwrap().pop_front() {
+            return Some(task);
+        }
+        let num_queues = self.queues.len();
+        (1..num_queues)
+            .map(|offset| (worker + offset) % num_queues)
+            .find_map(|victim| self.queues[victim].lock().unwrap().pop_back())
+    }
+}
+
+/// The limits of the search, shared by all the threads.
+struct Budget<'a> {
+    options: &'a SolveOptions,
+    /// The number of nodes explored by all the threads so far.
+    nodes: AtomicU64,
+    /// Set once one of the limits is hit, which also sets `stop`.
+    exhausted: AtomicBool,
+}
+
+impl<'a> Budget<'a> {
+    fn new(options: &'a SolveOptions) -> Self {
+        Budget {
+            options,
+            nodes: AtomicU64::new(0),
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether the deadline has passed or the search has been cancelled.
+    fn slow_limits_hit(&self) -> bool {
+        self.options
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+            || self
+                .options
+                .cancel_token
+                .as_ref()
+                .is_some_and(|cancel_token| cancel_token.load(Ordering::Relaxed))
+    }
+
+    fn exhaust(&self, stop: &AtomicBool) {
+        self.exhausted.store(true, Ordering::Relaxed);
+        stop.store(true, Ordering::Relaxed);
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::Relaxed)
+    }
+
+    /// The outcome of a search that found no solution.
+    fn outcome_without_solution(&self) -> SolveOutcome {
+        if self.is_exhausted() {
+            let nodes = self.nodes.load(Ordering::Relaxed);
+            SolveOutcome::Aborted {
+                nodes_explored: self.options.max_nodes.map_or(nodes, |max| nodes.min(max)),
+            }
+        } else {
+            SolveOutcome::NoSolution
+        }
+    }
+}
+
+/// Aborts the search of a task as soon as another thread sets `stop`, or once the limits of the
+/// budget are hit.
+///
+/// The nodes are added to the shared count in batches, except with a node limit, where every node
+/// counts.
+struct StopCounter<'a> {
+    stop: &'a AtomicBool,
+    budget: &'a Budget<'a>,
+    /// The nodes explored by this counter that aren't in the shared count yet.
+    nodes: u64,
+}
+
+impl StopCounter<'_> {
+    fn flush(&mut self) {
+        let before = self.budget.nodes.fetch_add(self.nodes, Ordering::Relaxed);
+        let after = before + self.nodes;
+        self.nodes = 0;
+        let over_node_limit = self
+            .budget
+            .options
+            .max_nodes
+            .is_some_and(|max_nodes| after > max_nodes);
+        // Like `BudgetCounter`, only check the slow limits every `CHECK_INTERVAL` nodes.
+        let check_slow_limits = before / CHECK_INTERVAL != after / CHECK_INTERVAL;
+        if over_node_limit || (check_slow_limits && self.budget.slow_limits_hit()) {
+            self.budget.exhaust(self.stop);
+        }
+    }
+}
+
+impl Drop for StopCounter<'_> {
+    fn drop(&mut self) {
+        if self.nodes > 0 {
+            self.flush();
+        }
+    }
+}
+
+impl IterationCounter for StopCounter<'_> {
+    #[inline]
+    fn increment(&mut self) {
+        self.nodes += 1;
+        if self.budget.options.max_nodes.is_some() || self.nodes == CHECK_INTERVAL {
+            self.flush();
+        }
+    }
+    #[inline]
+    fn get(&self) -> u64 {
+        self.budget.nodes.load(Ordering::Relaxed) + self.nodes
+    }
+    #[inline]
+    fn should_abort(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+}
+
+/// Run `work` on every task across `num_threads` threads, until all the tasks are done or
+/// `stop` is set. `work` gets a counter that aborts the search of its task once `stop` is set,
+/// or once the limits of the `budget` are hit.
+fn run<B, F, W>(
+    tasks: Vec<Task<B, F>>,
+    num_threads: usize,
+    stop: &AtomicBool,
+    budget: &Budget,
+    work: W,
+) where
+    B: Board + Send,
+    F: FacePolicy + Send,
+    W: Fn(usize, Task<B, F>, &mut StopCounter) + Sync,
+{
+    if budget.slow_limits_hit() {
+        budget.exhaust(stop);
+        return;
+    }
+    let num_threads = num_threads.max(1);
+    let queues = WorkQueues::new(tasks.into_iter().enumerate().collect(), num_threads);
+    std::thread::scope(|scope| {
+        for worker in 0..num_threads {
+            let queues = &queues;
+            let work = &work;
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    match queues.next(worker) {
+                        Some((index, task)) => {
+                            let mut counter = StopCounter {
+                                stop,
+                                budget,
+                                nodes: 0,
+                            };
+                            work(index, task, &mut counter)
+                        }
+                        None => break,
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// The number of threads to use by default: one per available core.
+pub fn default_num_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Find a solution to the puzzle using `num_threads` threads.
+/// The solution is the first one found by any thread, not necessarily the one `solve` returns.
+pub fn solve_parallel<B: Board + Send>(
+    pieces: &[PlacedPiece],
+    num_threads: usize,
+) -> Option<Vec<PlacedPiece>> {
+    solve_parallel_with_policy::<B, TenPieceFacePolicy>(pieces, num_threads)
+}
+
+/// Like `solve_parallel`, with the face policy `F` instead of the ten-piece rule.
+pub fn solve_parallel_with_policy<B: Board + Send, F: FacePolicy + Send>(
+    pieces: &[PlacedPiece],
+    num_threads: usize,
+) -> Option<Vec<PlacedPiece>> {
+    let root = SearchNode::<B, F>::root(pieces).map(|node| Task::root(node, pieces));
+    match solve_tasks(root, &SolveOptions::new(), num_threads) {
+        SolveOutcome::Solved(solution) => Some(solution),
+        _ => None,
+    }
+}
+
+/// Like `solve_with_options`, using `num_threads` threads.
+/// The node limit counts the nodes explored by all the threads.
+pub fn solve_parallel_with_options<B: Board + Send>(
+    pieces: &[PlacedPiece],
+    options: &SolveOptions,
+    num_threads: usize,
+) -> SolveOutcome {
+    match options.face_rule {
+        FaceRule::TenPiece => {
+            solve_parallel_with_options_impl::<B, TenPieceFacePolicy>(pieces, options, num_threads)
+        }
+        FaceRule::NinePiece => {
+            solve_parallel_with_options_impl::<B, NinePieceFacePolicy>(pieces, options, num_threads)
+        }
+        FaceRule::Unconstrained => {
+            solve_parallel_with_options_impl::<B, NoFacePolicy>(pieces, options, num_threads)
+        }
+    }
+}
+
+fn solve_parallel_with_options_impl<B: Board + Send, F: FacePolicy + Send>(
+    pieces: &[PlacedPiece],
+    options: &SolveOptions,
+    num_threads: usize,
+) -> SolveOutcome {
+    let root = match SearchNode::<B, F>::try_root(pieces, options.colors) {
+        Some(node) => Task::root(node, pieces),
+        None => return SolveOutcome::NoSolution,
+    };
+    solve_tasks(Some(root), options, num_threads)
+}
+
+/// Search the tree from `root` for a solution, with the pruning and limits of the `options`.
+fn solve_tasks<B: Board + Send, F: FacePolicy + Send>(
+    root: Option<Task<B, F>>,
+    options: &SolveOptions,
+    num_threads: usize,
+) -> SolveOutcome {
+    let tasks = split(root, options.pruning, num_threads);
+    let stop = AtomicBool::new(false);
+    let budget = Budget::new(options);
+    let solution = Mutex::new(None);
+    run(
+        tasks,
+        num_threads,
+        &stop,
+        &budget,
+        |_, mut task, counter| {
+            if let Some(pieces) = solve_rec(task.node, options.pruning, counter, &mut task.placed) {
+                stop.store(true, Ordering::Relaxed);
+                solution.lock().unwrap().get_or_insert(pieces);
+            }
+        },
+    );
+    match solution.into_inner().unwrap() {
+        Some(solution) => SolveOutcome::Solved(solution),
+        None => budget.outcome_without_solution(),
+    }
+}
+
+/// Count the solutions of the puzzle using `num_threads` threads.
+/// Like `count_solutions`, the search stops once `limit` solutions have been found.
+pub fn count_solutions_parallel<B: Board + Send>(
+    pieces: &[PlacedPiece],
+    limit: Option<u64>,
+    num_threads: usize,
+) -> u64 {
+    count_solutions_parallel_with_policy::<B, TenPieceFacePolicy>(pieces, limit, num_threads)
+}
+
+/// Like `count_solutions_parallel`, with the face policy `F` instead of the ten-piece rule.
+pub fn count_solutions_parallel_with_policy<B: Board + Send, F: FacePolicy + Send>(
+    pieces: &[PlacedPiece],
+    limit: Option<u64>,
+    num_threads: usize,
+) -> u64 {
+    let limit = limit.unwrap_or(u64::MAX);
+    if limit == 0 {
+        return 0;
+    }
+    let root = SearchNode::<B, F>::root(pieces).map(|node| Task::root(node, pieces));
+    let tasks = split(root, Pruning::default(), num_threads);
+    let stop = AtomicBool::new(false);
+    let options = SolveOptions::new();
+    let budget = Budget::new(&options);
+    let total = AtomicU64::new(0);
+    run(
+        tasks,
+        num_threads,
+        &stop,
+        &budget,
+        |_, mut task, counter| {
+            let remaining = limit.saturating_sub(total.load(Ordering::Relaxed));
+            if remaining == 0 {
+                stop.store(true, Ordering::Relaxed);
+                return;
+            }
+            let count = count_rec(
+                task.node,
+                Pruning::default(),
+                counter,
+                &mut task.placed,
+                remaining,
+            );
+            if total
+                .fetch_add(count, Ordering::Relaxed)
+                .saturating_add(count)
+                >= limit
+            {
+                stop.store(true, Ordering::Relaxed);
+            }
+        },
+    );
+    total.into_inner().min(limit)
+}
+
+/// Find every solution of the puzzle using `num_threads` threads.
+/// The solutions are returned in the same order as `solve_all`.
+pub fn solve_all_parallel<B: Board + Send>(
+    pieces: &[PlacedPiece],
+    num_threads: usize,
+) -> Vec<Vec<PlacedPiece>> {
+    solve_all_parallel_with_policy::<B, TenPieceFacePolicy>(pieces, num_threads)
+}
+
+/// Like `solve_all_parallel`, with the face policy `F` instead of the ten-piece rule.
+pub fn solve_all_parallel_with_policy<B: Board + Send, F: FacePolicy + Send>(
+    pieces: &[PlacedPiece],
+    num_threads: usize,
+) -> Vec<Vec<PlacedPiece>> {
+    let root = SearchNode::<B, F>::root(pieces).map(|node| Task::root(node, pieces));
+    let tasks = split(root, Pruning::default(), num_threads);
+    let stop = AtomicBool::new(false);
+    let options = SolveOptions::new();
+    let budget = Budget::new(&options);
+    let results = Mutex::new(Vec::new());
+    run(
+        tasks,
+        num_threads,
+        &stop,
+        &budget,
+        |index, mut task, counter| {
+            let mut solutions = Vec::new();
+            search_rec(
+                task.node,
+                Pruning::default(),
+                counter,
+                &mut task.placed,
+                &mut |solution: &[PlacedPiece]| {
+                    solutions.push(solution.to_vec());
+                    true
+                },
+            );
+            results.lock().unwrap().push((index, solutions));
+        },
+    );
+    let mut results = results.into_inner().unwrap();
+    results.sort_unstable_by_key(|(index, _)| *index);
+    results
+        .into_iter()
+        .flat_map(|(_, solutions)| solutions)
+        .collect()
+}
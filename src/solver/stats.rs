@@ -0,0 +1,137 @@
+//! Statistics about a search, to profile puzzles and tune the pruning rules.
+
+use super::IterationCounter;
+use crate::board::{Board, FailurePattern};
+use std::fmt;
+use std::time::Duration;
+
+/// What happened during a search.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchStats {
+    /// The number of nodes visited, indexed by the number of pieces on the board.
+    pub nodes_per_depth: [u64; 11],
+    /// The number of placements tried, i.e. the value of the iteration counter.
+    pub placements_tried: u64,
+    /// The number of nodes pruned by `Board::check_common_failures`, indexed by the
+    /// `FailurePattern` that fired.
+    pub pruned_by_pattern: [u64; FailurePattern::COUNT],
+    /// The number of nodes pruned because the pieces left couldn't cover the empty cells.
+    pub pruned_by_cell_count: u64,
+    /// The number of nodes pruned because a region of empty cells couldn't be filled.
+    pub pruned_by_regions: u64,
+    /// The number of faces skipped because the face policy didn't allow them.
+    pub face_policy_rejections: u64,
+    /// The number of placements that didn't fit on the board.
+    pub placement_failures: u64,
+    /// How long the search took. For searches run side by side and merged, the longest one.
+    pub elapsed: Duration,
+}
+
+impl SearchStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The total number of nodes visited.
+    pub fn nodes(&self) -> u64 {
+        self.nodes_per_depth.iter().sum()
+    }
+
+    /// The number of nodes pruned by the given pattern.
+    pub fn pruned_by(&self, pattern: FailurePattern) -> u64 {
+        self.pruned_by_pattern[pattern as usize]
+    }
+
+    /// The total number of nodes pruned, by any of the pruning rules.
+    pub fn pruned(&self) -> u64 {
+        self.pruned_by_pattern.iter().sum::<u64>()
+            + self.pruned_by_cell_count
+            + self.pruned_by_regions
+    }
+
+    /// Add the statistics of another search, e.g. from another thread.
+    /// The searches are assumed to run at the same time, so `elapsed` is the longest of the two.
+    pub fn merge(&mut self, other: &SearchStats) {
+        for (n, o) in self
+            .nodes_per_depth
+            .iter_mut()
+            .zip(other.nodes_per_depth.iter())
+        {
+            *n += o;
+        }
+        for (n, o) in self
+            .pruned_by_pattern
+            .iter_mut()
+            .zip(other.pruned_by_pattern.iter())
+        {
+            *n += o;
+        }
+        self.pruned_by_cell_count += other.pruned_by_cell_count;
+        self.pruned_by_regions += other.pruned_by_regions;
+        self.placements_tried += other.placements_tried;
+        self.face_policy_rejections += other.face_policy_rejections;
+        self.placement_failures += other.placement_failures;
+        self.elapsed = self.elapsed.max(other.elapsed);
+    }
+}
+
+impl IterationCounter for SearchStats {
+    #[inline]
+    fn increment(&mut self) {
+        self.placements_tried += 1;
+    }
+    #[inline]
+    fn get(&self) -> u64 {
+        self.placements_tried
+    }
+    fn record_node(&mut self, depth: u8) {
+        self.nodes_per_depth[depth as usize] += 1;
+    }
+    fn record_pruned<B: Board>(&mut self, board: &B) {
+        if let Some(pattern) = board.common_failure() {
+            self.pruned_by_pattern[pattern as usize] += 1;
+        }
+    }
+    fn record_cell_count_mismatch(&mut self) {
+        self.pruned_by_cell_count += 1;
+    }
+    fn record_dead_region(&mut self) {
+        self.pruned_by_regions += 1;
+    }
+    fn record_face_rejection(&mut self) {
+        self.face_policy_rejections += 1;
+    }
+    fn record_placement_failure(&mut self) {
+        self.placement_failures += 1;
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Elapsed: {:?}", self.elapsed)?;
+        writeln!(f, "Nodes: {}", self.nodes())?;
+        for (depth, nodes) in self.nodes_per_depth.iter().enumerate() {
+            if *nodes != 0 {
+                writeln!(f, "  depth {:>2}: {}", depth, nodes)?;
+            }
+        }
+        writeln!(f, "Placements tried: {}", self.placements_tried)?;
+        writeln!(f, "Placement failures: {}", self.placement_failures)?;
+        writeln!(f, "Face policy rejections: {}", self.face_policy_rejections)?;
+        writeln!(f, "Pruned: {}", self.pruned())?;
+        for &pattern in FailurePattern::ALL.iter() {
+            let pruned = self.pruned_by(pattern);
+            if pruned != 0 {
+                writeln!(f, "  {:?}: {}", pattern, pruned)?;
+            }
+        }
+        if self.pruned_by_cell_count != 0 {
+            writeln!(f, "  Cell count: {}", self.pruned_by_cell_count)?;
+        }
+        if self.pruned_by_regions != 0 {
+            writeln!(f, "  Regions: {}", self.pruned_by_regions)?;
+        }
+        Ok(())
+    }
+}
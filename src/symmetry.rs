@@ -0,0 +1,211 @@
+//! Symmetries of the board, to tell apart solutions that are really different.
+//!
+//! Turning the board by 180 degrees maps every solution to another one. Mirroring the board also
+//! mirrors the pieces: a mirrored piece is the same piece flipped over, on its other face, only
+//! if its shape happens to match. A mirrored solution is only valid if this holds for every
+//! piece.
+
+use crate::board::{placement_mask, placements, Board, HEIGHT, NUM_CELLS, WIDTH};
+use crate::pieces::*;
+use crate::solver::for_each_solution;
+
+/// A symmetry of the 5x10 board.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    Identity,
+    /// Turn the board by 180 degrees.
+    Rotation,
+    /// Swap the left and right of the board.
+    MirrorLeftRight,
+    /// Swap the top and bottom of the board.
+    MirrorTopBottom,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 4] = [
+        Symmetry::Identity,
+        Symmetry::Rotation,
+        Symmetry::MirrorLeftRight,
+        Symmetry::MirrorTopBottom,
+    ];
+
+    /// The image of the cell.
+    pub fn map_cell(self, cell: u8) -> u8 {
+        let (row, col) = (cell / WIDTH, cell % WIDTH);
+        match self {
+            Symmetry::Identity => cell,
+            Symmetry::Rotation => NUM_CELLS - 1 - cell,
+            Symmetry::MirrorLeftRight => row * WIDTH + WIDTH - 1 - col,
+            Symmetry::MirrorTopBottom => (HEIGHT - 1 - row) * WIDTH + col,
+        }
+    }
+
+    /// The image of a bitfield of cells.
+    pub fn map_mask(self, mask: u64) -> u64 {
+        (0..NUM_CELLS)
+            .filter(|cell| mask & (1 << cell) != 0)
+            .fold(0, |acc, cell| acc | 1 << self.map_cell(cell))
+    }
+
+    /// The image of the piece: the piece of the same color covering the image of its cells, or
+    /// None if no face and orientation of the piece has this shape.
+    ///
+    /// If several orientations have the same shape, the first one is used.
+    pub fn map_piece(self, piece: PlacedPiece) -> Option<PlacedPiece> {
+        let mask = self.map_mask(placement_mask(piece)?);
+        let top_left = mask.trailing_zeros() as u8;
+        placements(top_left, piece.piece.color())
+            .iter()
+            .find(|p| p.mask == mask)
+            .map(|p| PlacedPiece {
+                piece: p.piece(),
+                top_left,
+            })
+    }
+
+    /// The image of the pieces, sorted by top-left cell, or None if one of them has no image.
+    pub fn map_pieces(self, pieces: &[PlacedPiece]) -> Option<Vec<PlacedPiece>> {
+        let mut image = pieces
+            .iter()
+            .map(|p| self.map_piece(*p))
+            .collect::<Option<Vec<_>>>()?;
+        image.sort_by_key(|p| p.top_left);
+        Some(image)
+    }
+}
+
+/// The color of each cell, or 255 for empty cells. Solutions are ordered by this key.
+fn color_grid(pieces: &[PlacedPiece]) -> [u8; NUM_CELLS as usize] {
+    let mut grid = [255; NUM_CELLS as usize];
+    for p in pieces {
+        let mask = placement_mask(*p).unwrap_or(0);
+        for (cell, color) in grid.iter_mut().enumerate() {
+            if mask & (1 << cell) != 0 {
+                *color = p.piece.color() as u8;
+            }
+        }
+    }
+    grid
+}
+
+/// The canonical representative of the pieces: their smallest valid image by the symmetries,
+/// sorted by top-left cell. The pieces must fit on the board.
+///
+/// Two solutions are the same up to symmetry if and only if they have the same canonical form.
+pub fn canonical(pieces: &[PlacedPiece]) -> Vec<PlacedPiece> {
+    Symmetry::ALL
+        .iter()
+        .filter_map(|s| s.map_pieces(pieces))
+        .min_by_key(|image| color_grid(image))
+        .unwrap()
+}
+
+/// Whether the solution is the smallest of its images that are solutions of the puzzle made of
+/// `pieces`, i.e. whether it represents its class.
+fn is_class_representative(pieces: &[PlacedPiece], solution: &[PlacedPiece]) -> bool {
+    let given = color_grid(pieces);
+    let grid = color_grid(solution);
+    Symmetry::ALL[1..]
+        .iter()
+        .filter_map(|s| s.map_pieces(solution))
+        .map(|image| color_grid(&image))
+        .filter(|image| {
+            given
+                .iter()
+                .zip(image.iter())
+                .all(|(g, i)| *g == 255 || g == i)
+        })
+        .all(|image| image >= grid)
+}
+
+/// Count the solutions of the puzzle up to symmetry: solutions that are images of each other
+/// are only counted once.
+///
+/// If a `limit` is given, the search stops once that many classes have been found.
+pub fn count_solution_classes<B: Board>(pieces: &[PlacedPiece], limit: Option<u64>) -> u64 {
+    let limit = limit.unwrap_or(u64::MAX);
+    let mut count = 0;
+    for_each_solution::<B, _>(pieces, |solution| {
+        if is_class_representative(pieces, solution) {
+            count += 1;
+        }
+        count < limit
+    });
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BinaryBoard;
+    use crate::puzzles::*;
+    use crate::solver::{count_solutions, solve, solve_all};
+
+    fn sorted(pieces: &[PlacedPiece]) -> Vec<PlacedPiece> {
+        Symmetry::Identity.map_pieces(pieces).unwrap()
+    }
+
+    #[test]
+    fn map_cells() {
+        for s in Symmetry::ALL.iter() {
+            for cell in 0..NUM_CELLS {
+                assert_eq!(s.map_cell(s.map_cell(cell)), cell);
+            }
+        }
+        assert_eq!(Symmetry::Rotation.map_cell(12), 37);
+        assert_eq!(Symmetry::MirrorLeftRight.map_cell(12), 17);
+        assert_eq!(Symmetry::MirrorTopBottom.map_cell(12), 32);
+    }
+
+    #[test]
+    fn rotated_solution() {
+        let solution = solve::<BinaryBoard>(&*PIECES_117).unwrap();
+        let rotated = Symmetry::Rotation.map_pieces(&solution).unwrap();
+        // The rotated solution is a solution.
+        assert_eq!(
+            solve::<BinaryBoard>(&rotated).map(|s| sorted(&s)),
+            Some(rotated.clone())
+        );
+        assert_eq!(
+            Symmetry::Rotation
+                .map_pieces(&rotated)
+                .map(|s| color_grid(&s)),
+            Some(color_grid(&solution))
+        );
+        assert_eq!(canonical(&rotated), canonical(&solution));
+        assert_eq!(canonical(&canonical(&solution)), canonical(&solution));
+        // On the empty board, only one of them is counted.
+        assert_ne!(
+            is_class_representative(&[], &solution),
+            is_class_representative(&[], &rotated)
+        );
+        // With the pieces of the puzzle, the rotated solution isn't a solution.
+        assert!(is_class_representative(&*PIECES_117, &solution));
+        for s in Symmetry::ALL.iter() {
+            if let Some(image) = s.map_pieces(&solution) {
+                assert_eq!(canonical(&image), canonical(&solution));
+            }
+        }
+    }
+
+    #[test]
+    fn count_classes() {
+        // The solutions with the pieces of the bottom row removed.
+        let solution = solve::<BinaryBoard>(&*PIECES_117).unwrap();
+        let pieces: Vec<PlacedPiece> = solution
+            .iter()
+            .cloned()
+            .filter(|p| placement_mask(*p).unwrap() >> 40 == 0)
+            .collect();
+        let solutions = solve_all::<BinaryBoard>(&pieces);
+        let mut classes: Vec<Vec<PlacedPiece>> = solutions.iter().map(|s| canonical(s)).collect();
+        classes.sort_by_key(|c| color_grid(c));
+        classes.dedup();
+        assert_eq!(
+            count_solution_classes::<BinaryBoard>(&pieces, None),
+            classes.len() as u64
+        );
+        assert!(classes.len() as u64 <= count_solutions::<BinaryBoard>(&pieces, None));
+        assert_eq!(count_solution_classes::<BinaryBoard>(&pieces, Some(1)), 1);
+    }
+}
@@ -0,0 +1,224 @@
+//! Detailed checks of a partial board, e.g. one built by a player.
+
+use crate::board::{placement_mask, BinaryBoard, Board, FailurePattern, NUM_CELLS};
+use crate::pieces::*;
+use crate::solver::{
+    self, solve_with_options, FacePolicy, IterationCounter, SolveOptions, SolveOutcome,
+    TenPieceFacePolicy,
+};
+
+/// A problem with the pieces themselves. The pieces are given by their index in the list.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// The piece doesn't fit on the board.
+    OutOfBounds { piece: usize },
+    /// The two pieces cover some of the same cells.
+    Overlap { first: usize, second: usize },
+    /// The two pieces have the same color.
+    DuplicateColor { first: usize, second: usize },
+}
+
+/// Why a board can't be completed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DeadEnd {
+    /// There are more pieces on this face than the 6 A faces and 4 B faces of a solution.
+    TooManyFaces(Face),
+    /// The first empty cells form a hole that no piece can fill.
+    Hole(FailurePattern),
+    /// The pieces left can't cover exactly the empty cells.
+    CellCount,
+    /// A region of empty cells can't be filled exactly by the pieces left.
+    Region,
+    /// None of the rules above applies, but the search found no solution.
+    NoSolution,
+}
+
+/// Whether a valid board can be completed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Status {
+    /// The board is full.
+    Complete,
+    /// The board can be completed to a full solution.
+    Completable,
+    DeadEnd(DeadEnd),
+}
+
+/// Records which pruning rule found the dead end.
+#[derive(Default)]
+struct DeadEndRecorder {
+    reason: Option<DeadEnd>,
+}
+
+impl IterationCounter for DeadEndRecorder {
+    fn increment(&mut self) {}
+    fn get(&self) -> u64 {
+        0
+    }
+    fn record_pruned<B: Board>(&mut self, board: &B) {
+        self.reason = board.common_failure().map(DeadEnd::Hole);
+    }
+    fn record_cell_count_mismatch(&mut self) {
+        self.reason = Some(DeadEnd::CellCount);
+    }
+    fn record_dead_region(&mut self) {
+        self.reason = Some(DeadEnd::Region);
+    }
+}
+
+/// All the conflicts between the pieces, in the order of the pieces.
+pub fn conflicts(pieces: &[PlacedPiece]) -> Vec<Conflict> {
+    let masks: Vec<Option<u64>> = pieces
+        .iter()
+        .map(|p| {
+            if p.top_left < NUM_CELLS {
+                placement_mask(*p)
+            } else {
+                None
+            }
+        })
+        .collect();
+    let mut conflicts = Vec::new();
+    for (second, p) in pieces.iter().enumerate() {
+        let mask = match masks[second] {
+            Some(mask) => mask,
+            None => {
+                conflicts.push(Conflict::OutOfBounds { piece: second });
+                continue;
+            }
+        };
+        for (first, other) in pieces[..second].iter().enumerate() {
+            if other.piece.color() == p.piece.color() {
+                conflicts.push(Conflict::DuplicateColor { first, second });
+            }
+            if masks[first].is_some_and(|m| m & mask != 0) {
+                conflicts.push(Conflict::Overlap { first, second });
+            }
+        }
+    }
+    conflicts
+}
+
+/// Check the partial board made of `pieces`: either the list of conflicts between the pieces,
+/// or whether the board can still be completed.
+pub fn validate(pieces: &[PlacedPiece]) -> Result<Status, Vec<Conflict>> {
+    let conflicts = conflicts(pieces);
+    if !conflicts.is_empty() {
+        return Err(conflicts);
+    }
+    if let Some(face) = TenPieceFacePolicy::from_placed_pieces(pieces).exceeded_face() {
+        return Ok(Status::DeadEnd(DeadEnd::TooManyFaces(face)));
+    }
+    if pieces.len() == 10 {
+        return Ok(Status::Complete);
+    }
+    let mut recorder = DeadEndRecorder::default();
+    if solver::is_dead_end::<BinaryBoard, _>(pieces, &mut recorder) {
+        return Ok(Status::DeadEnd(
+            recorder.reason.unwrap_or(DeadEnd::NoSolution),
+        ));
+    }
+    match solve_with_options::<BinaryBoard>(pieces, &SolveOptions::new()) {
+        SolveOutcome::Solved(_) => Ok(Status::Completable),
+        _ => Ok(Status::DeadEnd(DeadEnd::NoSolution)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles::*;
+    use crate::solver::solve;
+
+    fn piece(color: Color, face: Face, top_left: u8) -> PlacedPiece {
+        PlacedPiece {
+            piece: Piece::new()
+                .with_color(color)
+                .with_face(face)
+                .with_orientation(Orientation::Up),
+            top_left,
+        }
+    }
+
+    #[test]
+    fn validate_conflicts() {
+        let pieces = [
+            piece(Color::Yellow, Face::A, 0),
+            piece(Color::Blue, Face::A, 49),
+            piece(Color::Yellow, Face::B, 1),
+            piece(Color::Red, Face::A, 0),
+        ];
+        assert_eq!(
+            validate(&pieces),
+            Err(vec![
+                Conflict::OutOfBounds { piece: 1 },
+                Conflict::DuplicateColor {
+                    first: 0,
+                    second: 2
+                },
+                Conflict::Overlap {
+                    first: 0,
+                    second: 2
+                },
+                Conflict::Overlap {
+                    first: 0,
+                    second: 3
+                },
+                Conflict::Overlap {
+                    first: 2,
+                    second: 3
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn validate_status() {
+        assert_eq!(validate(&*PIECES_117), Ok(Status::Completable));
+        assert_eq!(validate(&[]), Ok(Status::Completable));
+        let solution = solve::<BinaryBoard>(&*PIECES_117).unwrap();
+        assert_eq!(validate(&solution), Ok(Status::Complete));
+        assert_eq!(validate(&solution[..7]), Ok(Status::Completable));
+    }
+
+    #[test]
+    fn validate_dead_ends() {
+        let oriented = |color, orientation, top_left| PlacedPiece {
+            piece: Piece::new()
+                .with_color(color)
+                .with_face(Face::A)
+                .with_orientation(orientation),
+            top_left,
+        };
+        // The 2 cells in the bottom-right corner are cut off by the yellow and blue pieces.
+        assert_eq!(
+            validate(&[
+                oriented(Color::Yellow, Orientation::Down, 9),
+                oriented(Color::Blue, Orientation::Up, 16)
+            ]),
+            Ok(Status::DeadEnd(DeadEnd::Hole(FailurePattern::DoubleHole)))
+        );
+        // The 3 cells on the left of the yellow piece are too few for any piece.
+        assert_eq!(
+            validate(&[oriented(Color::Yellow, Orientation::Down, 1)]),
+            Ok(Status::DeadEnd(DeadEnd::Region))
+        );
+        assert_eq!(
+            validate(&[
+                piece(Color::Yellow, Face::A, 0),
+                piece(Color::Blue, Face::A, 2)
+            ]),
+            Ok(Status::DeadEnd(DeadEnd::NoSolution))
+        );
+        let faces_b = [
+            piece(Color::Yellow, Face::B, 0),
+            piece(Color::Orange, Face::B, 2),
+            piece(Color::Red, Face::B, 4),
+            piece(Color::Pink, Face::B, 6),
+            piece(Color::Blue, Face::B, 8),
+        ];
+        assert_eq!(
+            validate(&faces_b),
+            Ok(Status::DeadEnd(DeadEnd::TooManyFaces(Face::B)))
+        );
+    }
+}
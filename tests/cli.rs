@@ -0,0 +1,229 @@
+use iqfit_solver::board::BinaryBoard;
+use iqfit_solver::database::SolutionDatabase;
+use iqfit_solver::puzzles::PIECES_117;
+use iqfit_solver::solver::solve_all;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn iqfit(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_iqfit"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[test]
+fn solve_builtin() {
+    let output = iqfit(&["solve", "49", "--format", "ascii"], "");
+    assert_eq!(output.status.code(), Some(0));
+    let grid = stdout(&output);
+    assert_eq!(grid.lines().count(), 5);
+    assert!(!grid.contains('.'));
+}
+
+#[test]
+fn check_unique_from_stdin() {
+    let output = iqfit(
+        &["check-unique", "-", "--format", "json", "--backend", "dlx"],
+        "light-blue B right 3\ndeep-blue A left 34\n",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "{\"unique\":true,\"solutions\":1}\n");
+
+    let output = iqfit(&["check-unique", "-"], "yellow B up 10\n");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn validate_reports_position() {
+    let output = iqfit(&["validate", "-"], "yellow B up 10\nblue A up 10\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "-:2:11: overlaps the yellow piece\n");
+    // Other commands can't load an invalid puzzle.
+    let output = iqfit(&["solve", "-"], "yellow B up 10\nblue A up 10\n");
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn validate_builtin() {
+    let output = iqfit(&["validate", "117"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "Valid\n");
+    let output = iqfit(&["validate", "49", "--format", "json"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "{\"valid\":true}\n");
+}
+
+#[test]
+fn json_output() {
+    let output = iqfit(&["count", "49", "--format", "json", "--limit", "1"], "");
+    assert_eq!(stdout(&output), "{\"count\":1,\"limit_reached\":true}\n");
+    let output = iqfit(&["solve", "117", "--format", "json"], "");
+    let json: serde_json::Value = serde_json::from_str(stdout(&output)).unwrap();
+    assert_eq!(json["pieces"].as_array().unwrap().len(), 10);
+    assert_eq!(json["pieces"][0]["piece"]["color"], "light-blue");
+    assert_eq!(json["board"].as_array().unwrap().len(), 5);
+    let output = iqfit(&["rate", "117", "--format", "json"], "");
+    let json: serde_json::Value = serde_json::from_str(stdout(&output)).unwrap();
+    assert_eq!(json["level"], "Wizard");
+    assert_eq!(json["given_pieces"], 2);
+    let output = iqfit(
+        &["validate", "-", "--format", "json"],
+        "yellow B up 10\nblue A up 10\n",
+    );
+    assert_eq!(
+        stdout(&output),
+        "{\"valid\":false,\"error\":{\"line\":2,\"column\":11,\"message\":\"overlaps the yellow piece\"}}\n"
+    );
+}
+
+#[test]
+fn face_quota() {
+    let seven_faces_a = "yellow A up 0\nblue A up 2\ngreen A up 3\nred A down 4\n\
+                         pink A up 6\norange A up 8\npurple A down 21\n";
+    for command in ["solve", "count", "check-unique", "rate"].iter() {
+        let output = iqfit(&[command, "-"], seven_faces_a);
+        assert_eq!(output.status.code(), Some(1), "{}", command);
+        assert_eq!(stdout(&output), "");
+        assert_eq!(
+            std::str::from_utf8(&output.stderr).unwrap(),
+            "-: invalid puzzle: too many pieces on face A\n"
+        );
+    }
+    let output = iqfit(&["validate", "-", "--format", "json"], seven_faces_a);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "{\"valid\":false,\"error\":{\"message\":\"too many pieces on face A\"}}\n"
+    );
+}
+
+#[test]
+fn usage_errors() {
+    assert_eq!(iqfit(&[], "").status.code(), Some(2));
+    assert_eq!(iqfit(&["frobnicate"], "").status.code(), Some(2));
+    assert_eq!(
+        iqfit(&["count", "49", "--backend", "quantum"], "")
+            .status
+            .code(),
+        Some(2)
+    );
+    assert_eq!(iqfit(&["count", "12"], "").status.code(), Some(3));
+    for extra in [
+        ["--database", "solutions.db"],
+        ["--backend", "dlx"],
+        ["--backend", "parallel"],
+    ]
+    .iter()
+    {
+        let mut args = vec!["count", "49", "--distinct"];
+        args.extend_from_slice(extra);
+        assert_eq!(iqfit(&args, "").status.code(), Some(2), "{:?}", extra);
+    }
+}
+
+#[test]
+fn list_puzzles() {
+    let output = iqfit(&["list-puzzles"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        " 49: Expert, 3 pieces\n\
+         117: Wizard, 2 pieces\n\
+         2 of the 120 booklet puzzles have been transcribed\n"
+    );
+}
+
+#[test]
+fn rate_builtin() {
+    let output = iqfit(&["rate", "117"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).starts_with("Level: Wizard\n"));
+}
+
+#[test]
+fn lookup_in_database() {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("cli_solutions.db");
+    let database = SolutionDatabase::from_solutions(solve_all::<BinaryBoard>(&*PIECES_117));
+    database
+        .write(std::fs::File::create(&path).unwrap())
+        .unwrap();
+    let path = path.to_str().unwrap();
+    let output = iqfit(&["check-unique", "117", "--database", path], "");
+    assert_eq!(output.status.code(), Some(0));
+    let output = iqfit(&["count", "49", "--database", path], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "Solutions: 0\n");
+
+    std::fs::write(path, b"not a database").unwrap();
+    let output = iqfit(&["count", "117", "--database", path], "");
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn home_made_pieces() {
+    let block = "5 A 0,1 1,1 2,1 3,1 4,1 B 0,1 1,1 2,1 3,1 4,1";
+    let shapes = format!(
+        "k0 {0}\nk1 {0}\nk2 {0}\nk3 {0}\nnotch 5 A 0,1 1,1 2,1 3,1 B 0,1 1,1 2,1 3,1\ndot 1 A B\n",
+        block
+    );
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("cli_blocks.txt");
+    std::fs::write(&path, shapes).unwrap();
+    let path = path.to_str().unwrap();
+    let puzzle = "k0 A up 0\nk1 A up 2\nk2 A up 4\nk3 A up 6\n";
+    for backend in ["binary", "display", "dlx"].iter() {
+        let output = iqfit(
+            &["count", "-", "--pieces", path, "--backend", backend],
+            puzzle,
+        );
+        assert_eq!(output.status.code(), Some(0), "{}", backend);
+        assert_eq!(stdout(&output), "Solutions: 32\n", "{}", backend);
+    }
+    let output = iqfit(
+        &["solve", "-", "--pieces", path, "--format", "ascii"],
+        puzzle,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        format!("{}notch A up 8\ndot A up 49\n", puzzle)
+    );
+    let output = iqfit(
+        &["validate", "-", "--pieces", path],
+        "k0 A up 0\nk1 A up 1\n",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "-:2:9: overlaps the k0 piece\n");
+
+    // Booklet puzzles and some commands only work with the standard pieces.
+    assert_eq!(
+        iqfit(&["solve", "49", "--pieces", path], "").status.code(),
+        Some(2)
+    );
+    assert_eq!(
+        iqfit(&["rate", "-", "--pieces", path], "").status.code(),
+        Some(2)
+    );
+    std::fs::write(path, "k0 3 A -128,0 B\n").unwrap();
+    // The shape file is read before the puzzle.
+    let output = iqfit(&["count", "-", "--pieces", path], "");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        std::str::from_utf8(&output.stderr).unwrap(),
+        format!("{}:1:8: invalid ball '-128,0'\n", path)
+    );
+}